@@ -0,0 +1,277 @@
+use base64::Engine;
+use base64::alphabet;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use crate::charset;
+use crate::config::{ByteEncoding, ByteEncodingTransformation};
+use crate::transformer::Transformer;
+
+const QUOTED_PRINTABLE_LINE_LENGTH: usize = 76;
+/// Characters per line of the hex dump including the line break.
+const HEX_DUMP_LINE_LENGTH: usize = 79;
+/// Position of the first hex byte and the first ASCII character within a hex dump line.
+const HEX_DUMP_HEX_COLUMN: usize = 10;
+const HEX_DUMP_ASCII_COLUMN: usize = 61;
+
+/// Encodes text as Base64, hex or quoted-printable, or decodes it.
+#[derive(Debug, Clone)]
+pub struct ByteCodecTransformer {
+    scheme: ByteEncoding,
+    charset: String,
+    decode: bool,
+}
+
+impl ByteCodecTransformer {
+    pub fn encoder(trafo_config: &ByteEncodingTransformation) -> anyhow::Result<ByteCodecTransformer> {
+        return Self::from_config(trafo_config, false);
+    }
+
+    pub fn decoder(trafo_config: &ByteEncodingTransformation) -> anyhow::Result<ByteCodecTransformer> {
+        return Self::from_config(trafo_config, true);
+    }
+
+    fn from_config(trafo_config: &ByteEncodingTransformation, decode: bool) -> anyhow::Result<ByteCodecTransformer> {
+        if charset::encode(trafo_config.charset(), "").is_none() {
+            return Err(anyhow::Error::msg(format!("Unknown charset \"{}\".", trafo_config.charset())));
+        }
+        return Ok(ByteCodecTransformer {
+            scheme: trafo_config.scheme(),
+            charset: trafo_config.charset().to_string(),
+            decode,
+        });
+    }
+
+    fn encode(&self, text: &str) -> anyhow::Result<String> {
+        let data = charset::encode(self.charset.as_str(), text)
+            .ok_or_else(|| anyhow::Error::msg(format!("The text cannot be encoded as {}.", self.charset)))?;
+        match self.scheme {
+            ByteEncoding::Base64 => return Ok(base64_engine(true).encode(data)),
+            ByteEncoding::Base64Url => return Ok(base64_engine(false).encode(data)),
+            ByteEncoding::Hex => return Ok(data.iter().map(|byte| format!("{:02x}", byte)).collect()),
+            ByteEncoding::QuotedPrintable => return Ok(encode_quoted_printable(data.as_slice())),
+        }
+    }
+
+    fn decode(&self, text: &str) -> anyhow::Result<String> {
+        let data = match self.scheme {
+            ByteEncoding::Base64 | ByteEncoding::Base64Url => {
+                let compact: String = text.chars().filter(|ch| !ch.is_whitespace()).collect();
+                // both alphabets are accepted, so URL-safe Base64 can be decoded by either scheme
+                let compact = compact.replace('-', "+").replace('_', "/");
+                base64_engine(true).decode(compact)
+                    .or_else(|err| Err(anyhow::Error::msg(format!("Invalid Base64: {}", err))))?
+            }
+            ByteEncoding::Hex => decode_hex(text)?,
+            ByteEncoding::QuotedPrintable => decode_quoted_printable(text)?,
+        };
+
+        if let Some(text) = charset::decode(self.charset.as_str(), data.as_slice()) {
+            if !charset::contains_control_chars(text.as_str()) {
+                return Ok(text);
+            }
+        }
+        return Ok(hex_dump(data.as_slice()));
+    }
+}
+
+impl Transformer for ByteCodecTransformer {
+    fn execute(&self, text: &str) -> anyhow::Result<String> {
+        if self.decode {
+            return self.decode(text);
+        }
+        return self.encode(text);
+    }
+}
+
+/// Formats bytes like `hexdump -C`: offset, 16 hex bytes and their printable ASCII characters.
+pub fn hex_dump(data: &[u8]) -> String {
+    let mut output = String::new();
+    for (line, chunk) in data.chunks(16).enumerate() {
+        let mut hex = String::new();
+        for index in 0..16 {
+            if index == 8 {
+                hex.push(' ');
+            }
+            match chunk.get(index) {
+                Some(byte) => hex += format!("{:02x} ", byte).as_str(),
+                None => hex += "   ",
+            }
+        }
+        let ascii: String = chunk.iter()
+            .map(|byte| if byte.is_ascii_graphic() || *byte == b' ' { *byte as char } else { '.' })
+            .collect();
+        output += format!("{:08x}  {} |{}|\n", line * 16, hex, ascii).as_str();
+    }
+    return output;
+}
+
+/// Returns the character ranges (start inclusive, end exclusive) of the hex and ASCII columns of
+/// the bytes `start..end` within the output of [`hex_dump`].
+pub fn hex_dump_ranges(start: usize, end: usize) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for index in start..end {
+        let line = (index / 16) * HEX_DUMP_LINE_LENGTH;
+        let column = index % 16;
+        let hex = line + HEX_DUMP_HEX_COLUMN + column * 3 + if column >= 8 { 1 } else { 0 };
+        ranges.push((hex, hex + 2));
+        let ascii = line + HEX_DUMP_ASCII_COLUMN + column;
+        ranges.push((ascii, ascii + 1));
+    }
+    return ranges;
+}
+
+fn base64_engine(standard: bool) -> GeneralPurpose {
+    let config = GeneralPurposeConfig::new()
+        .with_encode_padding(standard)
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent);
+    if standard {
+        return GeneralPurpose::new(&alphabet::STANDARD, config);
+    }
+    return GeneralPurpose::new(&alphabet::URL_SAFE, config);
+}
+
+/// Decodes hex digits. Whitespace, `:` and `-` separators and `0x` or `\x` prefixes are ignored.
+fn decode_hex(text: &str) -> anyhow::Result<Vec<u8>> {
+    let digits: String = text.replace("0x", "").replace("0X", "").replace("\\x", "")
+        .chars()
+        .filter(|ch| !ch.is_whitespace() && *ch != ':' && *ch != '-')
+        .collect();
+    if let Some(ch) = digits.chars().find(|ch| !ch.is_ascii_hexdigit()) {
+        return Err(anyhow::Error::msg(format!("Invalid hex digit '{}'.", ch)));
+    }
+    if !digits.len().is_multiple_of(2) {
+        return Err(anyhow::Error::msg("Hex data has an odd number of digits."));
+    }
+    return Ok((0..digits.len()).step_by(2)
+        .map(|index| u8::from_str_radix(&digits[index..index + 2], 16).unwrap())
+        .collect());
+}
+
+/// Encodes bytes as quoted-printable (RFC 2045), keeping line breaks of the text.
+fn encode_quoted_printable(data: &[u8]) -> String {
+    let mut output = String::new();
+    let mut line_length = 0;
+    for (index, byte) in data.iter().enumerate() {
+        if *byte == b'\n' {
+            output.push('\n');
+            line_length = 0;
+            continue;
+        }
+        let next = data.get(index + 1);
+        let at_line_end = next.is_none() || next == Some(&b'\n') || next == Some(&b'\r');
+        let encoded = if *byte == b'\r' && next == Some(&b'\n') {
+            String::new()
+        } else if ((*byte == b' ' || *byte == b'\t') && !at_line_end) || ((33..=126).contains(byte) && *byte != b'=') {
+            (*byte as char).to_string()
+        } else {
+            format!("={:02X}", byte)
+        };
+        // a soft line break keeps lines below the maximum length
+        if line_length + encoded.len() > QUOTED_PRINTABLE_LINE_LENGTH - 1 {
+            output += "=\n";
+            line_length = 0;
+        }
+        line_length += encoded.len();
+        output += encoded.as_str();
+    }
+    return output;
+}
+
+fn decode_quoted_printable(text: &str) -> anyhow::Result<Vec<u8>> {
+    let bytes = text.as_bytes();
+    let mut data: Vec<u8> = Vec::new();
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] != b'=' {
+            data.push(bytes[index]);
+            index += 1;
+            continue;
+        }
+        let rest = &bytes[index + 1..];
+        if rest.len() >= 2 && rest[0].is_ascii_hexdigit() && rest[1].is_ascii_hexdigit() {
+            data.push(u8::from_str_radix(std::str::from_utf8(&rest[..2])?, 16)?);
+            index += 3;
+            continue;
+        }
+        // a soft line break, optionally with trailing whitespace
+        let whitespace = rest.iter().take_while(|byte| **byte == b' ' || **byte == b'\t').count();
+        let after = &rest[whitespace..];
+        let line_break = if after.starts_with(b"\r\n") { 2 } else if after.starts_with(b"\n") { 1 } else { 0 };
+        if line_break == 0 && after.len() > 0 {
+            return Err(anyhow::Error::msg(format!("Invalid quoted-printable escape at position {}.", index + 1)));
+        }
+        index += 1 + whitespace + line_break;
+    }
+    return Ok(data);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoder(yaml: &str) -> ByteCodecTransformer {
+        return ByteCodecTransformer::encoder(&serde_yaml::from_str(yaml).unwrap()).unwrap();
+    }
+
+    fn decoder(yaml: &str) -> ByteCodecTransformer {
+        return ByteCodecTransformer::decoder(&serde_yaml::from_str(yaml).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn encodes_and_decodes_base64() {
+        assert_eq!(encoder("{ scheme: base64 }").execute("Grüße ??>").unwrap(), "R3LDvMOfZSA/Pz4=");
+        assert_eq!(encoder("{ scheme: base64url }").execute("Grüße ??>").unwrap(), "R3LDvMOfZSA_Pz4");
+        assert_eq!(decoder("{ scheme: base64 }").execute("R3LDvMOf\nZSA/Pz4=\n").unwrap(), "Grüße ??>");
+        assert_eq!(decoder("{ scheme: base64 }").execute("R3LDvMOfZSA_Pz4").unwrap(), "Grüße ??>");
+        assert!(decoder("{ scheme: base64 }").execute("R3L*").is_err());
+    }
+
+    #[test]
+    fn encodes_and_decodes_hex() {
+        assert_eq!(encoder("{ scheme: hex, charset: utf-16le }").execute("Hi").unwrap(), "48006900");
+        assert_eq!(decoder("{ scheme: hex }").execute("48:65:6c 6c 6f").unwrap(), "Hello");
+        assert_eq!(decoder("{ scheme: hex, charset: iso-8859-1 }").execute("0xfc").unwrap(), "ü");
+        assert!(decoder("{ scheme: hex }").execute("4g").is_err());
+        assert!(decoder("{ scheme: hex }").execute("486").is_err());
+    }
+
+    #[test]
+    fn shows_binary_data_as_hex_dump() {
+        assert_eq!(decoder("{ scheme: hex }").execute("00 01 41").unwrap(),
+                   "00000000  00 01 41                                          |..A|\n");
+    }
+
+    #[test]
+    fn round_trips_quoted_printable() {
+        let text = "Grüße aus Köln = schön. ".repeat(5) + " \nnext line ";
+        let encoded = encoder("{ scheme: quoted_printable }").execute(text.as_str()).unwrap();
+        assert!(encoded.starts_with("Gr=C3=BC=C3=9Fe aus K=C3=B6ln =3D sch=C3=B6n. "));
+        assert!(encoded.lines().all(|line| line.len() <= QUOTED_PRINTABLE_LINE_LENGTH), "{}", encoded);
+        assert!(encoded.ends_with("=20\nnext line=20"), "{}", encoded);
+        assert_eq!(decoder("{ scheme: quoted_printable }").execute(encoded.as_str()).unwrap(), text);
+    }
+
+    #[test]
+    fn maps_bytes_to_hex_dump_columns() {
+        let data: Vec<u8> = (0u8..40).map(|byte| byte + 60).collect();
+        let dump: Vec<char> = hex_dump(data.as_slice()).chars().collect();
+        let columns: Vec<String> = hex_dump_ranges(7, 9).into_iter().chain(hex_dump_ranges(17, 18))
+            .map(|(start, end)| dump[start..end].iter().collect())
+            .collect();
+        // bytes 7 and 8 are separated by the gap in the middle of the hex column
+        assert_eq!(columns, vec!["43", "C", "44", "D", "4d", "M"]);
+    }
+
+    #[test]
+    fn dumps_offsets_partial_lines_and_non_printable_bytes() {
+        let data: Vec<u8> = (0u8..16).map(|byte| byte + b'a').chain([b' ', 0x00, 0x7f, 0xff]).collect();
+        let dump = hex_dump(data.as_slice());
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(lines, vec![
+            "00000000  61 62 63 64 65 66 67 68  69 6a 6b 6c 6d 6e 6f 70  |abcdefghijklmnop|",
+            "00000010  20 00 7f ff                                       | ...|",
+        ]);
+        // each full line including the line break has the length hex_dump_ranges() relies on
+        assert_eq!(lines[0].len() + 1, HEX_DUMP_LINE_LENGTH);
+        assert_eq!(hex_dump(&[]), "");
+    }
+}
@@ -0,0 +1,468 @@
+use std::str::FromStr;
+use encoding::all::{ASCII, ISO_8859_1, ISO_8859_15, UTF_16BE, UTF_16LE, UTF_8};
+use encoding::label::encoding_from_whatwg_label;
+use encoding::{all, DecoderTrap, EncoderTrap, Encoding, EncodingRef};
+
+/// Encodings of the `encoding` crate which are not offered in the encoding dropdown: internal
+/// encodings and duplicates of the encodings handled by [`decode`] itself.
+const HIDDEN_ENCODINGS: [&str; 3] = ["error", "encoder-only-utf-8", "ascii"];
+
+/// How bytes which are invalid in the encoding are decoded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DecodeTrap {
+    /// Decoding stops at the first invalid byte sequence.
+    Strict,
+    /// Each invalid byte sequence becomes U+FFFD.
+    Replace,
+    /// Each invalid byte becomes `\xNN`.
+    Escape,
+}
+
+/// A byte sequence which is invalid in the encoding.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidBytes {
+    pub start: usize,
+    pub end: usize,
+    /// Index of the character in the decoded text the sequence has been replaced at.
+    pub char_index: usize,
+}
+
+/// The result of [`decode_trapped`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrappedText {
+    /// The decoded text, in strict mode only the text before the first invalid byte sequence.
+    pub text: String,
+    /// All invalid byte sequences, in strict mode including those after the first one.
+    pub invalid_bytes: Vec<InvalidBytes>,
+    /// Byte offset of each character followed by the end offset like [`char_offsets`], if known.
+    pub char_offsets: Option<Vec<usize>>,
+}
+
+/// Returns the ids and display names of all supported encodings: the encodings handled by [`decode`]
+/// itself, followed by the encodings of the `encoding` crate under their WHATWG name.
+pub fn encodings() -> Vec<(String, String)> {
+    let mut result: Vec<(String, String)> = [
+        ("utf-8", "UTF-8"),
+        ("utf-16", "UTF-16 (byte order mark)"),
+        ("utf-16le", "UTF-16LE"),
+        ("utf-16be", "UTF-16BE"),
+        ("iso-8859-1", "ISO-8859-1 (Latin-1)"),
+        ("iso-8859-15", "ISO-8859-15 (Latin-1 with euro sign)"),
+        ("us-ascii", "US-ASCII"),
+    ].iter().map(|(id, name)| (id.to_string(), name.to_string())).collect();
+    for encoding in all::encodings() {
+        let id = encoding.whatwg_name().unwrap_or(encoding.name());
+        if HIDDEN_ENCODINGS.contains(&encoding.name()) || result.iter().any(|(existing, _name)| existing == id) {
+            continue;
+        }
+        let name = if id != encoding.name() {
+            format!("{} ({})", id, encoding.name())
+        } else {
+            id.to_string()
+        };
+        result.push((id.to_string(), name));
+    }
+    return result;
+}
+
+/// Finds an encoding of the `encoding` crate by its WHATWG label (e.g. `cp1252`, `latin2`, `sjis`)
+/// or its name.
+pub fn lookup(label: &str) -> Option<EncodingRef> {
+    let label = label.trim().to_lowercase();
+    return encoding_from_whatwg_label(label.as_str())
+        .or_else(|| all::encodings().iter().find(|encoding| encoding.name() == label.as_str()).copied());
+}
+
+/// Returns the id of an encoding as listed by [`encodings`] for a charset name or alias, e.g.
+/// `windows-1252` for `cp1252`.
+pub fn canonical_name(label: &str) -> Option<String> {
+    let label = label.trim().to_lowercase();
+    match label.as_str() {
+        "utf-8" | "utf-16le" | "utf-16be" | "iso-8859-1" | "iso-8859-15" | "us-ascii" => return Some(label),
+        "utf8" => return Some("utf-8".to_string()),
+        "utf-16" | "unicode" => return Some("utf-16".to_string()),
+        "ascii" => return Some("us-ascii".to_string()),
+        _ => {
+            let encoding = lookup(label.as_str())?;
+            return Some(encoding.whatwg_name().unwrap_or(encoding.name()).to_string());
+        }
+    }
+}
+
+/// Decodes bytes using the encoding with the given name, e.g. `utf-8`, `utf-16` (with byte order
+/// mark) or `iso-8859-1`. Other names are looked up in the `encoding` crate, see [`lookup`].
+/// Returns `None` for unknown encodings or invalid UTF-8.
+pub fn decode(encoding: &str, data: &[u8]) -> Option<String> {
+    match encoding.to_lowercase().as_str() {
+        "utf-8" => {
+            return String::from_utf8(data.to_vec()).ok();
+        },
+        "utf-16le" => {
+            return UTF_16LE.decode(data, DecoderTrap::Replace).ok();
+        },
+        "utf-16be" => {
+            return UTF_16BE.decode(data, DecoderTrap::Replace).ok();
+        },
+        "utf-16" | "unicode" => {
+            // without a byte order mark, the native byte order is used
+            return encoding_ref(encoding, data)?.decode(data, DecoderTrap::Replace).ok();
+        },
+        "iso-8859-1" => {
+            // real Latin-1 as used by the STRING target, WHATWG maps this label to windows-1252
+            return ISO_8859_1.decode(data, DecoderTrap::Replace).ok();
+        },
+        "iso-8859-15" => {
+            return ISO_8859_15.decode(data, DecoderTrap::Replace).ok();
+        },
+        "us-ascii" | "ascii" => {
+            return ASCII.decode(data, DecoderTrap::Replace).ok();
+        },
+        _ => {
+            return lookup(encoding)?.decode(data, DecoderTrap::Replace).ok();
+        }
+    }
+}
+
+/// Decodes bytes like [`decode`], but reports the position of every invalid byte sequence and
+/// handles them according to `trap`. Returns `None` for unknown encodings and for decoders like
+/// `rtf` which are not text encodings.
+pub fn decode_trapped(encoding: &str, data: &[u8], trap: DecodeTrap) -> Option<TrappedText> {
+    let encoding_ref = encoding_ref(encoding, data)?;
+    let mut decoder = encoding_ref.raw_decoder();
+    let mut text = String::new();
+    let mut invalid_bytes: Vec<InvalidBytes> = Vec::new();
+    let mut char_offsets: Option<Vec<usize>> = Some(Vec::new());
+    let mut char_count = 0;
+    let mut remaining = 0;
+    // same loop as `Encoding::decode_to`, keeping track of the valid segments between the errors
+    loop {
+        let text_len = text.len();
+        let (offset, err) = decoder.raw_feed(&data[remaining..], &mut text);
+        let unprocessed = remaining + offset;
+        let err = match err {
+            Some(err) => Some(err),
+            None => decoder.raw_finish(&mut text).map(|mut err| {
+                err.upto += (data.len() - remaining) as isize;
+                return err;
+            }),
+        };
+        let segment_end = if err.is_some() { unprocessed } else { data.len() };
+        let segment = &text[text_len..];
+        char_count += segment.chars().count();
+        char_offsets = char_offsets.and_then(|mut offsets| {
+            let segment_offsets = self::char_offsets(encoding, &data[remaining..segment_end], segment)?;
+            offsets.extend(segment_offsets[..segment_offsets.len() - 1].iter().map(|offset| remaining + offset));
+            return Some(offsets);
+        });
+        let err = match err {
+            Some(err) => err,
+            None => break,
+        };
+        let end = (remaining as isize + err.upto) as usize;
+        invalid_bytes.push(InvalidBytes {
+            start: unprocessed,
+            end,
+            char_index: char_count,
+        });
+        match trap {
+            DecodeTrap::Escape => {
+                for (index, byte) in data[unprocessed..end].iter().enumerate() {
+                    text += format!("\\x{:02X}", byte).as_str();
+                    char_count += 4;
+                    if let Some(offsets) = char_offsets.as_mut() {
+                        offsets.extend([unprocessed + index; 4]);
+                    }
+                }
+            }
+            DecodeTrap::Replace | DecodeTrap::Strict => {
+                text.push('\u{fffd}');
+                char_count += 1;
+                if let Some(offsets) = char_offsets.as_mut() {
+                    offsets.push(unprocessed);
+                }
+            }
+        }
+        remaining = end;
+        if remaining >= data.len() {
+            break;
+        }
+    }
+    let mut char_offsets = char_offsets.map(|mut offsets| {
+        offsets.push(data.len());
+        return offsets;
+    });
+    if trap == DecodeTrap::Strict {
+        if let Some(first) = invalid_bytes.first() {
+            let text_end = text.char_indices().nth(first.char_index).map(|(index, _ch)| index).unwrap_or(text.len());
+            text.truncate(text_end);
+            char_offsets = char_offsets.map(|mut offsets| {
+                offsets.truncate(first.char_index);
+                offsets.push(first.start);
+                return offsets;
+            });
+        }
+    }
+    return Some(TrappedText {
+        text,
+        invalid_bytes,
+        char_offsets,
+    });
+}
+
+/// Returns the encoding of the `encoding` crate [`decode`] uses for the given name. `utf-16` uses
+/// the byte order mark of the data.
+fn encoding_ref(encoding: &str, data: &[u8]) -> Option<EncodingRef> {
+    match encoding.to_lowercase().as_str() {
+        "utf-8" => return Some(UTF_8),
+        "utf-16le" => return Some(UTF_16LE),
+        "utf-16be" => return Some(UTF_16BE),
+        "utf-16" | "unicode" => {
+            if data.starts_with(&[0xFE, 0xFF]) || (cfg!(target_endian = "big") && !data.starts_with(&[0xFF, 0xFE])) {
+                return Some(UTF_16BE);
+            }
+            return Some(UTF_16LE);
+        },
+        "iso-8859-1" => return Some(ISO_8859_1),
+        "iso-8859-15" => return Some(ISO_8859_15),
+        "us-ascii" | "ascii" => return Some(ASCII),
+        _ => return lookup(encoding),
+    }
+}
+
+/// Encodes the text using the encoding with the given name. Returns `None` for unknown encodings
+/// or if the text contains characters which cannot be encoded.
+pub fn encode(encoding: &str, text: &str) -> Option<Vec<u8>> {
+    match encoding.to_lowercase().as_str() {
+        "utf-8" => {
+            return Some(text.as_bytes().to_vec());
+        },
+        "utf-16le" => {
+            return UTF_16LE.encode(text, EncoderTrap::Strict).ok();
+        },
+        "utf-16be" => {
+            return UTF_16BE.encode(text, EncoderTrap::Strict).ok();
+        },
+        "utf-16" | "unicode" => {
+            // little endian with byte order mark, as written by Windows
+            let mut data = vec![0xFFu8, 0xFEu8];
+            data.append(&mut UTF_16LE.encode(text, EncoderTrap::Strict).ok()?);
+            return Some(data);
+        },
+        "iso-8859-1" => {
+            return ISO_8859_1.encode(text, EncoderTrap::Strict).ok();
+        },
+        "iso-8859-15" => {
+            return ISO_8859_15.encode(text, EncoderTrap::Strict).ok();
+        },
+        "us-ascii" | "ascii" => {
+            return ASCII.encode(text, EncoderTrap::Strict).ok();
+        },
+        _ => {
+            return lookup(encoding)?.encode(text, EncoderTrap::Strict).ok();
+        }
+    }
+}
+
+/// Encodes the text like [`encode`], but replaces characters the encoding cannot represent by their
+/// ASCII transliteration (e.g. `“` by `"`, `€` by `EUR`) or by `?`. Returns `None` for unknown encodings.
+pub fn encode_transliterated(encoding: &str, text: &str) -> Option<Vec<u8>> {
+    return encode_replaced(encoding, text, |ch| {
+        return deunicode::deunicode_char(ch)
+            .map(|replacement| replacement.trim())
+            .filter(|replacement| replacement.len() > 0 && encode(encoding, replacement).is_some())
+            .unwrap_or("?")
+            .to_string();
+    });
+}
+
+/// Encodes the text like [`encode`], but replaces characters the encoding cannot represent by the
+/// result of `replace`. Returns `None` for unknown encodings or if a replacement cannot be encoded either.
+pub fn encode_replaced<F: Fn(char) -> String>(encoding: &str, text: &str, replace: F) -> Option<Vec<u8>> {
+    if let Some(data) = encode(encoding, text) {
+        return Some(data);
+    }
+    encode(encoding, "")?;
+    let mut buffer = [0u8; 4];
+    let mut replaced = String::new();
+    for ch in text.chars() {
+        if encode(encoding, ch.encode_utf8(&mut buffer)).is_some() {
+            replaced.push(ch);
+        } else {
+            replaced += replace(ch).as_str();
+        }
+    }
+    return encode(encoding, replaced.as_str());
+}
+
+/// Returns the first character of the text the encoding cannot represent.
+pub fn first_unencodable(encoding: &str, text: &str) -> Option<char> {
+    let mut buffer = [0u8; 4];
+    return text.chars().find(|ch| encode(encoding, ch.encode_utf8(&mut buffer)).is_none());
+}
+
+/// Returns the byte order mark of a Unicode encoding, `None` for encodings without one. `utf-16`
+/// is always encoded with its (little endian) byte order mark, see [`encode`].
+pub fn byte_order_mark(encoding: &str) -> Option<&'static [u8]> {
+    match encoding.to_lowercase().as_str() {
+        "utf-8" => return Some(&[0xEF, 0xBB, 0xBF]),
+        "utf-16le" | "utf-16" | "unicode" => return Some(&[0xFF, 0xFE]),
+        "utf-16be" => return Some(&[0xFE, 0xFF]),
+        _ => return None,
+    }
+}
+
+/// Returns the charset of a clipboard target: `UTF8_STRING` is UTF-8, `STRING` and `TEXT` are
+/// Latin-1 (ICCCM) and MIME types use their `charset` parameter, `text/...` types default to UTF-8.
+pub fn target_charset(target: &str) -> Option<String> {
+    match target.to_lowercase().as_str() {
+        "utf8_string" => return Some("utf-8".to_string()),
+        "string" | "text" => return Some("iso-8859-1".to_string()),
+        target => {
+            let content_type = mime::Mime::from_str(target).ok()?;
+            if let Some(charset) = content_type.get_param("charset") {
+                return Some(charset.as_str().to_lowercase());
+            }
+            if content_type.type_() == mime::TEXT {
+                return Some("utf-8".to_string());
+            }
+            return None;
+        }
+    }
+}
+
+/// Returns the byte offset of each character of `text`, which has been decoded from `data`, followed
+/// by the end offset. Returns `None` if the offsets cannot be determined, e.g. for unknown encodings
+/// or if invalid bytes have been replaced.
+pub fn char_offsets(encoding: &str, data: &[u8], text: &str) -> Option<Vec<usize>> {
+    let encoding = encoding.to_lowercase();
+    let mut offsets: Vec<usize> = Vec::new();
+    let mut offset = 0;
+    let mut buffer = [0u8; 4];
+    for ch in text.chars() {
+        offsets.push(offset);
+        offset += match encoding.as_str() {
+            "utf-8" => ch.len_utf8(),
+            "utf-16le" | "utf-16be" | "utf-16" | "unicode" => ch.len_utf16() * 2,
+            "iso-8859-1" | "iso-8859-15" | "us-ascii" | "ascii" => 1,
+            // only correct for stateless encodings, otherwise the total length does not match
+            _ => encode(encoding.as_str(), ch.encode_utf8(&mut buffer))?.len(),
+        };
+    }
+    offsets.push(offset);
+    if offset != data.len() {
+        return None;
+    }
+    return Some(offsets);
+}
+
+/// Returns the bytes (start inclusive, end exclusive) the characters `start..end` have been decoded
+/// from, using the offsets returned by [`char_offsets`].
+pub fn byte_range(char_offsets: &[usize], start: usize, end: usize) -> Option<(usize, usize)> {
+    // the last offset is the end of the data
+    let last = char_offsets.len().checked_sub(1)?;
+    if start >= last {
+        return None;
+    }
+    return Some((char_offsets[start], char_offsets[end.min(last)]));
+}
+
+/// Returns `true` if the text contains control characters other than tab and line breaks.
+pub fn contains_control_chars(text: &str) -> bool {
+    for ch in text.chars() {
+        if ch < (32 as char) && ch != '\t' && ch != '\n' && ch != '\r' {
+            return true;
+        }
+    }
+
+    return false;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_whatwg_labels() {
+        assert_eq!(canonical_name("cp1252").as_deref(), Some("windows-1252"));
+        assert_eq!(canonical_name("sjis").as_deref(), Some("shift_jis"));
+        assert_eq!(canonical_name(" UTF8 ").as_deref(), Some("utf-8"));
+        assert_eq!(canonical_name("unicode").as_deref(), Some("utf-16"));
+        // latin1 is windows-1252 for WHATWG, only the exact name is real Latin-1
+        assert_eq!(canonical_name("latin1").as_deref(), Some("windows-1252"));
+        assert_eq!(canonical_name("no-such-charset"), None);
+    }
+
+    #[test]
+    fn lists_each_encoding_once() {
+        let encodings = encodings();
+        let ids: Vec<&str> = encodings.iter().map(|(id, _name)| id.as_str()).collect();
+        assert_eq!(&ids[..3], &["utf-8", "utf-16", "utf-16le"]);
+        assert!(ids.contains(&"koi8-r") && ids.contains(&"gbk"));
+        assert!(!ids.contains(&"error") && !ids.contains(&"ascii"));
+        assert!(ids.iter().enumerate().all(|(index, id)| !ids[..index].contains(id)));
+    }
+
+    #[test]
+    fn decodes_legacy_encodings() {
+        assert_eq!(decode("cp1252", &[0x93, 0x80, 0x94]).unwrap(), "“€”");
+        assert_eq!(decode("iso-8859-1", &[0x93, 0xE4]).unwrap(), "\u{93}ä");
+        assert_eq!(decode("shift_jis", encode("shift_jis", "日本語").unwrap().as_slice()).unwrap(), "日本語");
+        assert_eq!(encode("koi8-r", "Привет").unwrap(), vec![0xF0, 0xD2, 0xC9, 0xD7, 0xC5, 0xD4]);
+        assert_eq!(decode("no-such-charset", b"a"), None);
+    }
+
+    #[test]
+    fn decodes_utf16_by_byte_order_mark() {
+        assert_eq!(decode("utf-16", &[0xFF, 0xFE, b'a', 0, 0x3D, 0xD8, 0x00, 0xDE]).unwrap(), "\u{feff}a😀");
+        assert_eq!(decode("utf-16", &[0xFE, 0xFF, 0, b'a']).unwrap(), "\u{feff}a");
+        assert_eq!(decode("utf-16", &[]).unwrap(), "");
+        assert_eq!(decode("utf-16", &[b'a']).unwrap(), "\u{fffd}");
+    }
+
+    #[test]
+    fn lists_invalid_byte_offsets() {
+        let data = b"ab\xC3\xA4c\xFFd\xE2\x82";
+        let expected_invalid = vec![
+            InvalidBytes { start: 5, end: 6, char_index: 4 },
+            InvalidBytes { start: 7, end: 9, char_index: 6 },
+        ];
+        let replaced = decode_trapped("utf-8", data, DecodeTrap::Replace).unwrap();
+        assert_eq!(replaced.text, "abäc\u{fffd}d\u{fffd}");
+        assert_eq!(replaced.invalid_bytes, expected_invalid);
+        assert_eq!(replaced.char_offsets, Some(vec![0, 1, 2, 4, 5, 6, 7, 9]));
+
+        let strict = decode_trapped("utf-8", data, DecodeTrap::Strict).unwrap();
+        assert_eq!(strict.text, "abäc");
+        assert_eq!(strict.invalid_bytes, expected_invalid);
+        assert_eq!(strict.char_offsets, Some(vec![0, 1, 2, 4, 5]));
+
+        let escaped = decode_trapped("utf-8", data, DecodeTrap::Escape).unwrap();
+        assert_eq!(escaped.text, "abäc\\xFFd\\xE2\\x82");
+        // the character indices count the escapes of earlier invalid bytes
+        assert_eq!(escaped.invalid_bytes, vec![
+            InvalidBytes { start: 5, end: 6, char_index: 4 },
+            InvalidBytes { start: 7, end: 9, char_index: 9 },
+        ]);
+        assert_eq!(escaped.char_offsets.unwrap()[4..10], [5, 5, 5, 5, 6, 7]);
+    }
+
+    #[test]
+    fn traps_invalid_bytes_of_other_encodings() {
+        let trapped = decode_trapped("us-ascii", b"a\x80b", DecodeTrap::Replace).unwrap();
+        assert_eq!(trapped.text, "a\u{fffd}b");
+        assert_eq!(trapped.invalid_bytes, vec![InvalidBytes { start: 1, end: 2, char_index: 1 }]);
+        let trapped = decode_trapped("utf-8", "valid ä".as_bytes(), DecodeTrap::Strict).unwrap();
+        assert_eq!(trapped.text, "valid ä");
+        assert_eq!(trapped.invalid_bytes, vec![]);
+    }
+
+    #[test]
+    fn maps_selected_characters_to_their_bytes() {
+        let offsets = char_offsets("utf-8", "aäb".as_bytes(), "aäb").unwrap();
+        assert_eq!(offsets, vec![0, 1, 3, 4]);
+        assert_eq!(byte_range(&offsets, 1, 2), Some((1, 3)));
+        assert_eq!(byte_range(&offsets, 1, 10), Some((1, 4)));
+        assert_eq!(byte_range(&offsets, 3, 3), None);
+        assert_eq!(byte_range(&[], 0, 1), None);
+    }
+}
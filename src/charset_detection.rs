@@ -0,0 +1,219 @@
+use crate::charset;
+
+/// Number of bytes analysed, larger targets are detected from their beginning.
+const SAMPLE_LENGTH: usize = 64 * 1024;
+/// Legacy encodings scored by the characters their non-ASCII bytes decode to if the data is not valid UTF-8. On equal
+/// scores, the first one wins.
+const CANDIDATES: [&str; 12] = [
+    "windows-1252", "windows-1250", "iso-8859-15", "windows-1251", "koi8-r", "windows-1253",
+    "shift_jis", "euc-jp", "gbk", "big5", "euc-kr", "iso-8859-2",
+];
+
+/// The detected encoding (an id of [`charset::encodings`]) with a confidence between 0 and 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectedCharset {
+    pub encoding: String,
+    pub confidence: f64,
+}
+
+impl DetectedCharset {
+    fn new(encoding: &str, confidence: f64) -> DetectedCharset {
+        return DetectedCharset {
+            encoding: encoding.to_string(),
+            confidence,
+        };
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+    Hebrew,
+    Arabic,
+    Cjk,
+    Other,
+}
+
+/// Guesses the encoding of text without a declared charset from byte order marks, UTF-8 validity,
+/// the null bytes of UTF-16 and the characters the non-ASCII bytes decode to in legacy encodings.
+/// Returns `None` for binary data.
+pub fn detect_charset(data: &[u8]) -> Option<DetectedCharset> {
+    if data.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        return Some(DetectedCharset::new("utf-8", 1.0));
+    }
+    if data.starts_with(&[0xFF, 0xFE]) || data.starts_with(&[0xFE, 0xFF]) {
+        return Some(DetectedCharset::new("utf-16", 1.0));
+    }
+    let sample = &data[..data.len().min(SAMPLE_LENGTH)];
+    if let Some(detected) = detect_utf16(sample) {
+        return Some(detected);
+    }
+    let controls = sample.iter()
+        .filter(|byte| **byte < 0x20 && **byte != b'\t' && **byte != b'\n' && **byte != b'\r' && **byte != 0x0C)
+        .count();
+    if controls * 20 > sample.len() {
+        return None;
+    }
+
+    // a sample cut off in the middle of a character is still valid UTF-8
+    let valid_up_to = match std::str::from_utf8(sample) {
+        Ok(_) => sample.len(),
+        Err(err) => if err.error_len().is_none() { err.valid_up_to() } else { 0 },
+    };
+    if valid_up_to > 0 || sample.len() == 0 {
+        let text = std::str::from_utf8(&sample[..valid_up_to]).unwrap_or("");
+        let multibyte = text.chars().filter(|ch| !ch.is_ascii()).count();
+        if multibyte == 0 {
+            // ASCII is valid in all candidates, UTF-8 is its most likely superset
+            return Some(DetectedCharset::new("utf-8", 1.0));
+        }
+        // random legacy bytes rarely form valid UTF-8 sequences
+        return Some(DetectedCharset::new("utf-8", (0.8 + 0.05 * multibyte as f64).min(0.99)));
+    }
+
+    let mut scores: Vec<(&str, f64)> = CANDIDATES.iter()
+        .filter_map(|candidate| score(candidate, sample).map(|score| (*candidate, score)))
+        .collect();
+    // stable sort, so the order of the candidates decides between equal scores
+    scores.sort_by(|(_, first), (_, second)| second.partial_cmp(first).unwrap_or(std::cmp::Ordering::Equal));
+    let (best, best_score) = scores.first()?;
+    let second_score = scores.get(1).map(|(_, score)| *score).unwrap_or(0.0);
+    let confidence = best_score * (0.5 + (best_score - second_score)).min(1.0);
+    return Some(DetectedCharset::new(best, confidence.max(0.01)));
+}
+
+/// Text in UTF-16 has a null byte in every other position, as long as it is mostly ASCII.
+fn detect_utf16(sample: &[u8]) -> Option<DetectedCharset> {
+    if sample.len() < 4 || !sample.len().is_multiple_of(2) {
+        return None;
+    }
+    let pairs = (sample.len() / 2) as f64;
+    let even_nulls = sample.iter().step_by(2).filter(|byte| **byte == 0).count() as f64 / pairs;
+    let odd_nulls = sample.iter().skip(1).step_by(2).filter(|byte| **byte == 0).count() as f64 / pairs;
+    if odd_nulls > 0.3 && even_nulls < 0.05 {
+        return Some(DetectedCharset::new("utf-16le", (0.5 + odd_nulls).min(0.99)));
+    }
+    if even_nulls > 0.3 && odd_nulls < 0.05 {
+        return Some(DetectedCharset::new("utf-16be", (0.5 + even_nulls).min(0.99)));
+    }
+    return None;
+}
+
+/// Returns the plausibility (0 to 1) of the characters the non-ASCII bytes decode to, or `None`
+/// if the data is invalid in the encoding. Letters continuing a word of the same script are
+/// plausible, changes of the script or the case within a word, rare symbols and control characters are not.
+fn score(encoding: &str, sample: &[u8]) -> Option<f64> {
+    let text = charset::decode(encoding, sample)?;
+    let mut points = 0.0;
+    let mut count = 0;
+    let mut previous: Option<char> = None;
+    // accented Latin letters are rare enough to seldom follow each other, unlike Cyrillic or Greek letters
+    let mut latin_run = 0;
+    for ch in text.chars() {
+        if ch.is_ascii() {
+            previous = Some(ch);
+            latin_run = 0;
+            continue;
+        }
+        count += 1;
+        let ch_script = script(ch);
+        latin_run = if ch.is_alphabetic() && ch_script == Script::Latin { latin_run + 1 } else { 0 };
+        if ch == '\u{fffd}' || ch.is_control() || ('\u{e000}'..='\u{f8ff}').contains(&ch) {
+            points -= 3.0;
+        } else if ch.is_alphabetic() {
+            points += 1.0;
+            if ch_script == Script::Cjk {
+                // without spaces, any decoding forms words, so only frequent characters count
+                if is_frequent_cjk(ch) {
+                    points += 1.0;
+                }
+            } else if let Some(previous) = previous.filter(|previous| previous.is_alphabetic()) {
+                if script(previous) == ch_script || previous.is_ascii() && ch_script == Script::Latin {
+                    points += 1.0;
+                } else {
+                    points -= 2.0;
+                }
+                if previous.is_lowercase() && ch.is_uppercase() {
+                    points -= 1.0;
+                }
+            }
+            if latin_run >= 3 {
+                points -= 2.0;
+            }
+        } else if "“”„‘’‚–—…€«»°§©®·•\u{a0}".contains(ch) {
+            points += 0.5;
+        } else {
+            points -= 0.5;
+        }
+        previous = Some(ch);
+    }
+    if count == 0 {
+        return Some(1.0);
+    }
+    // -3 (only control characters) to 2 (only letters within words) per character
+    return Some(((points / count as f64 + 3.0) / 5.0).clamp(0.0, 1.0));
+}
+
+/// Kana, Hangul syllables and the most frequent Han characters of simplified and traditional Chinese.
+fn is_frequent_cjk(ch: char) -> bool {
+    const FREQUENT_HAN: &str = "的一是不了人我在有他这這中大来來上个個国國到说說们們为為子和你地出道也时時年得就那要下以生会會自着著去之过過家学學对對可里裡后後小么麼心多天而能好都然没沒日于於起还還发發成事只作当當想看文无無开開手十用主行方又如前所本见見经經头頭面公同三已老从從动動两兩长長";
+    match ch as u32 {
+        0x3040..=0x30FF | 0xAC00..=0xD7A3 => return true,
+        _ => return FREQUENT_HAN.contains(ch),
+    }
+}
+
+fn script(ch: char) -> Script {
+    match ch as u32 {
+        0x0041..=0x024F | 0x1E00..=0x1EFF => return Script::Latin,
+        0x0370..=0x03FF => return Script::Greek,
+        0x0400..=0x04FF => return Script::Cyrillic,
+        0x0590..=0x05FF => return Script::Hebrew,
+        0x0600..=0x06FF => return Script::Arabic,
+        0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xAC00..=0xD7A3 | 0xFF66..=0xFF9F => return Script::Cjk,
+        _ => return Script::Other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(encoding: &str, text: &str) -> DetectedCharset {
+        return detect_charset(charset::encode(encoding, text).unwrap().as_slice()).unwrap();
+    }
+
+    #[test]
+    fn detects_byte_order_marks() {
+        assert_eq!(detect_charset(b"\xEF\xBB\xBFabc"), Some(DetectedCharset::new("utf-8", 1.0)));
+        assert_eq!(detect_charset(b"\xFF\xFEa\0"), Some(DetectedCharset::new("utf-16", 1.0)));
+        assert_eq!(detect_charset(b"\xFE\xFF\0a"), Some(DetectedCharset::new("utf-16", 1.0)));
+    }
+
+    #[test]
+    fn detects_unicode_without_byte_order_mark() {
+        assert_eq!(detect_charset(b"plain ascii"), Some(DetectedCharset::new("utf-8", 1.0)));
+        assert_eq!(detect("utf-8", "Grüße “x”").encoding, "utf-8");
+        assert_eq!(detect("utf-16le", "Hello world").encoding, "utf-16le");
+        assert_eq!(detect("utf-16be", "Hello world").encoding, "utf-16be");
+    }
+
+    #[test]
+    fn detects_legacy_encodings() {
+        assert_eq!(detect("windows-1252", "Grüße aus Köln, schöne Straße").encoding, "windows-1252");
+        assert_eq!(detect("windows-1251", "Привет, как дела? Это тестовый текст.").encoding, "windows-1251");
+        assert_eq!(detect("koi8-r", "Привет, как дела? Это тестовый текст.").encoding, "koi8-r");
+        assert_eq!(detect("windows-1253", "Καλημέρα κόσμε, αυτό είναι ελληνικά").encoding, "windows-1253");
+        assert_eq!(detect("shift_jis", "日本語のテキストです。こんにちは").encoding, "shift_jis");
+        assert_eq!(detect("euc-kr", "안녕하세요 한국어 텍스트입니다").encoding, "euc-kr");
+        let detected = detect("windows-1251", "Привет, как дела? Это тестовый текст.");
+        assert!(detected.confidence > 0.0 && detected.confidence < 1.0, "{:?}", detected);
+    }
+
+    #[test]
+    fn rejects_binary_data() {
+        assert_eq!(detect_charset(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 0x89, b'P']), None);
+    }
+}
@@ -0,0 +1,72 @@
+use std::str::FromStr;
+use crate::selection::Selection;
+
+pub const USAGE: &str = "Usage: clipboard-cleaner [--selection clipboard|primary|secondary] [--output-selection clipboard|primary|secondary]";
+
+/// The selection whose targets are shown and cleaned, and the selection the result is written to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SelectionOptions {
+    pub selection: Selection,
+    /// Defaults to `selection`.
+    pub output_selection: Option<Selection>,
+    /// `-h` or `--help` was given, the usage is printed instead of starting the application.
+    pub help: bool,
+}
+
+/// Parses the command line arguments (without the program name).
+pub fn parse_args(args: &[String]) -> anyhow::Result<SelectionOptions> {
+    let mut options = SelectionOptions {
+        selection: Selection::Clipboard,
+        output_selection: None,
+        help: false,
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (arg.as_str(), None),
+        };
+        let mut next_value = || value.clone().or_else(|| args.next().cloned())
+            .ok_or_else(|| anyhow::Error::msg(format!("Missing value of {}.", name)));
+        match name {
+            "--selection" => options.selection = Selection::from_str(next_value()?.as_str())?,
+            "--output-selection" => options.output_selection = Some(Selection::from_str(next_value()?.as_str())?),
+            "-h" | "--help" => options.help = true,
+            _ => return Err(anyhow::Error::msg(format!("Unknown argument \"{}\".", arg))),
+        }
+    }
+    return Ok(options);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> anyhow::Result<SelectionOptions> {
+        return parse_args(&args.iter().map(|arg| arg.to_string()).collect::<Vec<String>>());
+    }
+
+    #[test]
+    fn defaults_to_the_clipboard_selection() {
+        let options = parse(&[]).unwrap();
+        assert_eq!(options, SelectionOptions { selection: Selection::Clipboard, output_selection: None, help: false });
+        assert!(parse(&["--help"]).unwrap().help);
+    }
+
+    #[test]
+    fn parses_separate_and_inline_values() {
+        let options = parse(&["--selection", "primary", "--output-selection=clipboard"]).unwrap();
+        assert_eq!(options.selection, Selection::Primary);
+        assert_eq!(options.output_selection, Some(Selection::Clipboard));
+    }
+
+    #[test]
+    fn rejects_unknown_flags_and_missing_values() {
+        let err = parse(&["--selections", "primary"]).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown argument \"--selections\".");
+        let err = parse(&["--output-selection"]).unwrap_err();
+        assert_eq!(err.to_string(), "Missing value of --output-selection.");
+        let err = parse(&["--selection=middle"]).unwrap_err();
+        assert!(err.to_string().starts_with("Unknown selection \"middle\""), "{}", err);
+    }
+}
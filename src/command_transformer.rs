@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+use wait_timeout::ChildExt;
+use crate::config::{CommandTransformation, Config};
+use crate::transformer::Transformer;
+use serde::{ Serialize, Deserialize };
+
+pub const DEFAULT_TIMEOUT_MS: u64 = 5_000;
+pub const MAX_TIMEOUT_MS: u64 = 300_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommandTransformer {
+    program: String,
+    args: Vec<String>,
+    env: HashMap<String, String>,
+    working_dir: Option<PathBuf>,
+    timeout: Duration,
+}
+
+impl CommandTransformer {
+    pub fn from_config(config: &Config, trafo_config: &CommandTransformation) -> anyhow::Result<CommandTransformer> {
+        let timeout_ms = trafo_config.timeout_ms().unwrap_or(DEFAULT_TIMEOUT_MS);
+        if !(1..=MAX_TIMEOUT_MS).contains(&timeout_ms) {
+            return Err(anyhow::Error::msg(format!(
+                "timeout_ms of a command must be between 1 and {}, not {}.", MAX_TIMEOUT_MS, timeout_ms)));
+        }
+        return Ok(CommandTransformer {
+            program: trafo_config.program().to_string(),
+            args: trafo_config.args().clone(),
+            env: trafo_config.env().clone(),
+            working_dir: trafo_config.working_dir().as_ref().map(|dir| config.resolve_path(dir.as_str())),
+            timeout: Duration::from_millis(timeout_ms),
+        });
+    }
+}
+
+impl Transformer for CommandTransformer {
+    fn execute(&self, text: &str) -> anyhow::Result<String> {
+        let mut command = Command::new(self.program.as_str());
+        command.args(self.args.iter())
+            .envs(self.env.iter())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if let Some(working_dir) = &self.working_dir {
+            command.current_dir(working_dir);
+        }
+
+        let mut child = command.spawn()
+            .or_else(|err| Err(anyhow::Error::msg(format!("Could not start \"{}\": {}", &self.program, err))))?;
+
+        // stdin, stdout and stderr are handled in separate threads, otherwise the program
+        // may block on a full pipe buffer.
+        let mut stdin = child.stdin.take().unwrap();
+        let input = text.as_bytes().to_vec();
+        let stdin_thread = thread::spawn(move || {
+            // the program may exit without reading all input, so write errors are ignored.
+            let _ = stdin.write_all(input.as_slice());
+        });
+        let stdout_thread = read_in_thread(child.stdout.take().unwrap());
+        let stderr_thread = read_in_thread(child.stderr.take().unwrap());
+
+        let status = match child.wait_timeout(self.timeout)? {
+            Some(status) => status,
+            None => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(anyhow::Error::msg(format!("\"{}\" did not finish within {} ms.",
+                                                      &self.program, self.timeout.as_millis())));
+            }
+        };
+        let _ = stdin_thread.join();
+        let stdout = stdout_thread.join().unwrap_or_default();
+        let stderr = stderr_thread.join().unwrap_or_default();
+
+        if !status.success() {
+            let stderr = String::from_utf8_lossy(stderr.as_slice());
+            let message = stderr.trim();
+            if message.len() > 0 {
+                return Err(anyhow::Error::msg(format!("\"{}\" failed ({}): {}", &self.program, status, message)));
+            }
+            return Err(anyhow::Error::msg(format!("\"{}\" failed ({}).", &self.program, status)));
+        }
+
+        return String::from_utf8(stdout)
+            .or_else(|_err| Err(anyhow::Error::msg(format!("Output of \"{}\" is not valid UTF-8.", &self.program))));
+    }
+}
+
+fn read_in_thread<R: Read + Send + 'static>(mut reader: R) -> thread::JoinHandle<Vec<u8>> {
+    return thread::spawn(move || {
+        let mut buffer: Vec<u8> = Vec::new();
+        let _ = reader.read_to_end(&mut buffer);
+        return buffer;
+    });
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn command(yaml: &str) -> anyhow::Result<CommandTransformer> {
+        let config = Config::default();
+        let trafo_config: CommandTransformation = serde_yaml::from_str(yaml).unwrap();
+        return CommandTransformer::from_config(&config, &trafo_config);
+    }
+
+    #[test]
+    fn pipes_text_through_the_command() {
+        let trafo = command("{ program: tr, args: [a-z, A-Z] }").unwrap();
+        assert_eq!(trafo.execute("hello\n").unwrap(), "HELLO\n");
+    }
+
+    #[test]
+    fn rejects_extreme_timeouts() {
+        assert!(command("{ program: cat, timeout_ms: 0 }").is_err());
+        let err = command("{ program: cat, timeout_ms: 18446744073709551615 }").unwrap_err();
+        assert!(err.to_string().contains("timeout_ms"), "{}", err);
+    }
+
+    #[test]
+    fn kills_commands_exceeding_the_timeout() {
+        let trafo = command("{ program: sleep, args: ['5'], timeout_ms: 100 }").unwrap();
+        assert!(trafo.execute("").unwrap_err().to_string().contains("did not finish within 100 ms"));
+    }
+
+    #[test]
+    fn reports_failures() {
+        let trafo = command("{ program: sh, args: [-c, 'echo broken >&2; exit 3'] }").unwrap();
+        assert!(trafo.execute("").unwrap_err().to_string().contains("broken"));
+    }
+}
@@ -1,15 +1,44 @@
 use std::collections::HashMap;
-use serde::{Serialize, Deserialize};
+use std::path::{Path, PathBuf};
+use serde::{Serialize, Deserialize, Deserializer};
+use serde::de::{DeserializeOwned, Error};
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Config {
     filters: HashMap<String, CharacterFilter>,
     profiles: Vec<TransformationProfile>,
     default_profile: Option<String>,
     gui_replacement_profile: Option<String>,
+    /// Rules used to detect secrets. The built-in rules are used if not set.
+    #[serde(default)]
+    secret_detection: Option<SecretDetection>,
+    /// Directory of the loaded configuration file. Relative paths in the configuration
+    /// (e.g. script files) are resolved against this directory.
+    #[serde(skip)]
+    base_dir: Option<PathBuf>,
 }
 
 impl Config {
+    pub fn base_dir(&self) -> &Option<PathBuf> {
+        return &self.base_dir;
+    }
+
+    pub fn set_base_dir(&mut self, base_dir: Option<PathBuf>) {
+        self.base_dir = base_dir;
+    }
+
+    /// Resolves a path from the configuration relative to the configuration file's directory.
+    pub fn resolve_path(&self, path: &str) -> PathBuf {
+        let path = Path::new(path);
+        if path.is_absolute() {
+            return path.to_path_buf();
+        }
+        if let Some(base_dir) = &self.base_dir {
+            return base_dir.join(path);
+        }
+        return path.to_path_buf();
+    }
+
     pub fn filters(&self) -> &HashMap<String, CharacterFilter> {
         return &self.filters;
     }
@@ -18,6 +47,66 @@ impl Config {
         return &self.profiles;
     }
 
+    pub fn profile(&self, name: &str) -> Option<&TransformationProfile> {
+        return self.profiles.iter()
+            .find(|profile| profile.name.as_str() == name);
+    }
+
+    /// Returns the transformations of the given profile with all `include`s and `extends`
+    /// resolved, in execution order.
+    pub fn resolve_transformations(&self, profile: &TransformationProfile) -> anyhow::Result<Vec<Transformation>> {
+        let mut stack: Vec<String> = Vec::new();
+        return self.resolve_transformations_recursive(profile, &mut stack);
+    }
+
+    fn resolve_transformations_recursive(&self, profile: &TransformationProfile, stack: &mut Vec<String>)
+                                         -> anyhow::Result<Vec<Transformation>> {
+        if stack.iter().any(|name| name.as_str() == profile.name()) {
+            stack.push(profile.name().to_string());
+            return Err(anyhow::Error::msg(format!("Cyclic profile composition: {}", stack.join(" -> "))));
+        }
+        stack.push(profile.name().to_string());
+
+        let mut result: Vec<Transformation> = Vec::new();
+        for name in profile.include().before() {
+            let included = self.referenced_profile(profile, "includes", name)?;
+            result.append(&mut self.resolve_transformations_recursive(included, stack)?);
+        }
+
+        if let Some(name) = profile.extends() {
+            let parent = self.referenced_profile(profile, "extends", name)?;
+            let mut inherited = self.resolve_transformations_recursive(parent, stack)?;
+            for trafo_override in profile.overrides() {
+                if trafo_override.index() >= inherited.len() {
+                    return Err(anyhow::Error::msg(format!(
+                        "Profile \"{}\" overrides transformation {}, but profile \"{}\" only has {} transformations.",
+                        profile.name(), trafo_override.index(), name, inherited.len())));
+                }
+                inherited[trafo_override.index()] = trafo_override.transformation().clone();
+            }
+            result.append(&mut inherited);
+        } else if profile.overrides().len() > 0 {
+            return Err(anyhow::Error::msg(format!(
+                "Profile \"{}\" defines overrides, but does not extend another profile.", profile.name())));
+        }
+
+        result.append(&mut profile.transformations().clone());
+
+        for name in profile.include().after() {
+            let included = self.referenced_profile(profile, "includes", name)?;
+            result.append(&mut self.resolve_transformations_recursive(included, stack)?);
+        }
+
+        stack.pop();
+        return Ok(result);
+    }
+
+    fn referenced_profile(&self, profile: &TransformationProfile, relation: &str, name: &str)
+                          -> anyhow::Result<&TransformationProfile> {
+        return self.profile(name).ok_or_else(|| anyhow::Error::msg(format!(
+            "Profile \"{}\" {} unknown profile \"{}\".", profile.name(), relation, name)));
+    }
+
     pub fn default_profile_name(&self) -> &Option<String> {
         return &self.default_profile;
     }
@@ -33,6 +122,10 @@ impl Config {
         return None;
     }
 
+    pub fn secret_detection(&self) -> &Option<SecretDetection> {
+        return &self.secret_detection;
+    }
+
     pub fn gui_replacement_profile_name(&self) -> &Option<String> {
         return &self.gui_replacement_profile;
     }
@@ -124,15 +217,140 @@ impl WrappedFilter {
     }
 }
 
+/// A transformation step of a profile. Each type is a map with a single key naming the type, e.g.
+/// `emoji: { mode: remove }`, except for character transformations, which have `filters` and `action`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Transformation {
+    /// Maps all characters matching one of the filters using the given action.
+    Character(CharacterTransformation),
+    /// Removes or describes emoji sequences.
+    Emoji {
+        emoji: EmojiTransformation,
+    },
+    /// Runs a sandboxed Rhai script on the text, each line or each character.
+    Script {
+        script: ScriptTransformation,
+    },
+    /// Pipes the text through an external program.
+    Command {
+        command: CommandTransformation,
+    },
+    /// Masks secrets like API keys, private keys or passwords.
+    MaskSecrets {
+        mask_secrets: SecretMaskTransformation,
+    },
+    /// Redacts personal data like email addresses, phone numbers or credit card numbers.
+    RedactPii {
+        redact_pii: PiiRedaction,
+    },
+    /// Cleans up code copied from terminals, REPLs and code viewers.
+    CodeSnippet {
+        code_snippet: CodeSnippetCleanup,
+    },
+    /// Converts HTML into Markdown, usually applied to the `text/html` target.
+    HtmlToMarkdown {
+        html_to_markdown: HtmlToMarkdownConversion,
+    },
+    /// Converts delimiter-separated cells (e.g. copied from a spreadsheet) into a Markdown table, CSV or JSON.
+    Table {
+        table: TableConversion,
+    },
+    /// Pretty-prints, minifies, validates or converts JSON, YAML, XML and TOML.
+    StructuredData {
+        structured_data: StructuredDataTransformation,
+    },
+    /// Encodes the (UTF-8 or `charset` encoded) text as Base64, hex or quoted-printable.
+    Encode {
+        encode: ByteEncodingTransformation,
+    },
+    /// Decodes Base64, hex or quoted-printable text and decodes the bytes using the `charset`.
+    /// Bytes which are not text are shown as hex dump.
+    Decode {
+        decode: ByteEncodingTransformation,
+    },
+    /// Converts a list of `file://` URIs (`text/uri-list`, `x-special/gnome-copied-files`) into paths.
+    FileList {
+        file_list: FileListConversion,
+    },
+    /// Repairs UTF-8 text which has been decoded as windows-1252 or Latin-1, e.g. `Ã¤` becomes `ä`.
+    RepairMojibake {
+        repair_mojibake: MojibakeRepair,
+    },
+    /// A transformation type registered in the `TransformerRegistry` by an embedding application.
+    Custom {
+        custom: CustomTransformation,
+    },
+}
+
+impl Transformation {
+    /// The keys naming the transformation types.
+    pub const TYPES: [&'static str; 14] = [
+        "emoji", "script", "command", "mask_secrets", "redact_pii", "code_snippet", "html_to_markdown", "table",
+        "structured_data", "encode", "decode", "file_list", "repair_mojibake", "custom",
+    ];
+
+    /// Dispatches on the type key, so errors name the transformation type and the invalid field
+    /// instead of not matching any variant.
+    fn from_value(value: serde_yaml::Value) -> Result<Transformation, String> {
+        let mapping = value.as_mapping()
+            .ok_or_else(|| "A transformation must be a map, e.g. `emoji: { mode: remove }`.".to_string())?;
+        if mapping.contains_key(&serde_yaml::Value::from("filters")) || mapping.contains_key(&serde_yaml::Value::from("action")) {
+            return serde_yaml::from_value(value.clone())
+                .map(Transformation::Character)
+                .map_err(|err| format!("Invalid character transformation: {}", err));
+        }
+        let keys = mapping.iter()
+            .map(|(key, _options)| key.as_str().unwrap_or("?").to_string())
+            .collect::<Vec<String>>();
+        if keys.len() != 1 {
+            return Err(format!("A transformation must have exactly one type key, found {}. Expected one of: {} (or `filters` and `action`).",
+                               if keys.len() == 0 { "none".to_string() } else { keys.join(", ") }, Transformation::TYPES.join(", ")));
+        }
+        let key = keys[0].as_str();
+        let options = mapping.iter().next().map(|(_key, options)| options.clone()).unwrap();
+        match key {
+            "emoji" => return Ok(Transformation::Emoji { emoji: parse_options(key, options)? }),
+            "script" => return Ok(Transformation::Script { script: parse_options(key, options)? }),
+            "command" => return Ok(Transformation::Command { command: parse_options(key, options)? }),
+            "mask_secrets" => return Ok(Transformation::MaskSecrets { mask_secrets: parse_options(key, options)? }),
+            "redact_pii" => return Ok(Transformation::RedactPii { redact_pii: parse_options(key, options)? }),
+            "code_snippet" => return Ok(Transformation::CodeSnippet { code_snippet: parse_options(key, options)? }),
+            "html_to_markdown" => return Ok(Transformation::HtmlToMarkdown { html_to_markdown: parse_options(key, options)? }),
+            "table" => return Ok(Transformation::Table { table: parse_options(key, options)? }),
+            "structured_data" => return Ok(Transformation::StructuredData { structured_data: parse_options(key, options)? }),
+            "encode" => return Ok(Transformation::Encode { encode: parse_options(key, options)? }),
+            "decode" => return Ok(Transformation::Decode { decode: parse_options(key, options)? }),
+            "file_list" => return Ok(Transformation::FileList { file_list: parse_options(key, options)? }),
+            "repair_mojibake" => return Ok(Transformation::RepairMojibake { repair_mojibake: parse_options(key, options)? }),
+            "custom" => return Ok(Transformation::Custom { custom: parse_options(key, options)? }),
+            _ => return Err(format!("Unknown transformation type \"{}\". Expected one of: {} (or `filters` and `action`).",
+                                    key, Transformation::TYPES.join(", "))),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Transformation {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_yaml::Value::deserialize(deserializer)?;
+        return Transformation::from_value(value).map_err(D::Error::custom);
+    }
+}
+
+fn parse_options<T: DeserializeOwned>(key: &str, options: serde_yaml::Value) -> Result<T, String> {
+    return serde_yaml::from_value(options)
+        .map_err(|err| format!("Invalid \"{}\" transformation: {}", key, err));
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Transformation {
+pub struct CharacterTransformation {
     filters: Vec<WrappedFilter>,
     action: TransformationAction,
 }
 
-impl Transformation {
-    pub fn new(filters: Vec<WrappedFilter>, action: TransformationAction) -> Transformation {
-        return Transformation {
+impl CharacterTransformation {
+    pub fn new(filters: Vec<WrappedFilter>, action: TransformationAction) -> CharacterTransformation {
+        return CharacterTransformation {
             filters,
             action
         };
@@ -147,11 +365,759 @@ impl Transformation {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum EmojiMode {
+    /// Removes the emoji sequence.
+    #[serde(rename = "remove")]
+    Remove,
+    /// Replaces the emoji sequence with its GitHub shortcode, e.g. `:rocket:`.
+    #[serde(rename = "shortcode")]
+    Shortcode,
+    /// Replaces the emoji sequence with its CLDR short name in brackets, e.g. `[rocket]`.
+    #[serde(rename = "name")]
+    Name,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmojiTransformation {
+    mode: EmojiMode,
+}
+
+impl EmojiTransformation {
+    pub fn new(mode: EmojiMode) -> EmojiTransformation {
+        return EmojiTransformation {
+            mode
+        };
+    }
+
+    pub fn mode(&self) -> &EmojiMode {
+        return &self.mode;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ScriptScope {
+    /// The script is run once with the whole text in the variable `text`.
+    #[serde(rename = "text")]
+    Text,
+    /// The script is run for each line (without line terminator) in the variable `line`.
+    #[serde(rename = "line")]
+    Line,
+    /// The script is run for each character in the variable `ch`.
+    #[serde(rename = "char")]
+    Char,
+}
+
+impl Default for ScriptScope {
+    fn default() -> Self {
+        return ScriptScope::Text;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScriptTransformation {
+    /// Inline script source.
+    #[serde(default)]
+    source: Option<String>,
+    /// Script file, relative to the configuration file's directory.
+    #[serde(default)]
+    file: Option<String>,
+    #[serde(default)]
+    scope: ScriptScope,
+    #[serde(default)]
+    max_operations: Option<u64>,
+    #[serde(default)]
+    timeout_ms: Option<u64>,
+}
+
+impl ScriptTransformation {
+    pub fn source(&self) -> &Option<String> {
+        return &self.source;
+    }
+    pub fn file(&self) -> &Option<String> {
+        return &self.file;
+    }
+    pub fn scope(&self) -> &ScriptScope {
+        return &self.scope;
+    }
+    pub fn max_operations(&self) -> Option<u64> {
+        return self.max_operations;
+    }
+    pub fn timeout_ms(&self) -> Option<u64> {
+        return self.timeout_ms;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommandTransformation {
+    program: String,
+    #[serde(default)]
+    args: Vec<String>,
+    /// Additional environment variables for the program.
+    #[serde(default)]
+    env: HashMap<String, String>,
+    /// Working directory, relative to the configuration file's directory.
+    #[serde(default)]
+    working_dir: Option<String>,
+    #[serde(default)]
+    timeout_ms: Option<u64>,
+}
+
+impl CommandTransformation {
+    pub fn program(&self) -> &str {
+        return &self.program;
+    }
+    pub fn args(&self) -> &Vec<String> {
+        return &self.args;
+    }
+    pub fn env(&self) -> &HashMap<String, String> {
+        return &self.env;
+    }
+    pub fn working_dir(&self) -> &Option<String> {
+        return &self.working_dir;
+    }
+    pub fn timeout_ms(&self) -> Option<u64> {
+        return self.timeout_ms;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SecretDetection {
+    /// Whether the built-in rules are used in addition to the configured rules.
+    #[serde(default = "default_true")]
+    default_rules: bool,
+    #[serde(default)]
+    rules: Vec<SecretRule>,
+    /// Detects random-looking tokens. Overrides the built-in entropy rule.
+    #[serde(default)]
+    entropy: Option<EntropyRule>,
+}
+
+impl SecretDetection {
+    pub fn new(default_rules: bool, rules: Vec<SecretRule>, entropy: Option<EntropyRule>) -> SecretDetection {
+        return SecretDetection {
+            default_rules,
+            rules,
+            entropy
+        };
+    }
+
+    pub fn default_rules(&self) -> bool {
+        return self.default_rules;
+    }
+    pub fn rules(&self) -> &Vec<SecretRule> {
+        return &self.rules;
+    }
+    pub fn entropy(&self) -> &Option<EntropyRule> {
+        return &self.entropy;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SecretRule {
+    name: String,
+    /// Regular expression matching the secret.
+    pattern: String,
+    /// Capture group containing the secret itself, e.g. only the value of `PASSWORD=value`.
+    /// Defaults to the whole match.
+    #[serde(default)]
+    group: Option<usize>,
+}
+
+impl SecretRule {
+    pub fn new(name: &str, pattern: &str, group: Option<usize>) -> SecretRule {
+        return SecretRule {
+            name: name.to_string(),
+            pattern: pattern.to_string(),
+            group
+        };
+    }
+
+    pub fn name(&self) -> &str {
+        return &self.name;
+    }
+    pub fn pattern(&self) -> &str {
+        return &self.pattern;
+    }
+    pub fn group(&self) -> Option<usize> {
+        return self.group;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EntropyRule {
+    /// Minimum length of a token (a run of letters, digits and `+/=_-`).
+    min_length: usize,
+    /// Minimum shannon entropy of a token in bits per character.
+    min_entropy: f64,
+}
+
+impl EntropyRule {
+    pub fn new(min_length: usize, min_entropy: f64) -> EntropyRule {
+        return EntropyRule {
+            min_length,
+            min_entropy
+        };
+    }
+
+    pub fn min_length(&self) -> usize {
+        return self.min_length;
+    }
+    pub fn min_entropy(&self) -> f64 {
+        return self.min_entropy;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SecretMaskMode {
+    /// Replaces the secret with `[REDACTED]`.
+    #[serde(rename = "full")]
+    Full,
+    /// Replaces all but the last 4 characters with `*`.
+    #[serde(rename = "last4")]
+    KeepLast4,
+    /// Replaces the secret with a short SHA-256 hash, so equal secrets can still be recognized.
+    #[serde(rename = "hash")]
+    Hash,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SecretMaskTransformation {
+    mode: SecretMaskMode,
+}
+
+impl SecretMaskTransformation {
+    pub fn mode(&self) -> &SecretMaskMode {
+        return &self.mode;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PiiCategory {
+    #[serde(rename = "email")]
+    Email,
+    #[serde(rename = "phone")]
+    Phone,
+    #[serde(rename = "iban")]
+    Iban,
+    #[serde(rename = "credit_card")]
+    CreditCard,
+    #[serde(rename = "ipv4")]
+    Ipv4,
+    #[serde(rename = "ipv6")]
+    Ipv6,
+}
+
+impl PiiCategory {
+    pub fn all() -> Vec<PiiCategory> {
+        return vec![PiiCategory::Email, PiiCategory::Phone, PiiCategory::Iban,
+                    PiiCategory::CreditCard, PiiCategory::Ipv4, PiiCategory::Ipv6];
+    }
+
+    /// The default replacement, e.g. `[EMAIL]`.
+    pub fn default_template(&self) -> &'static str {
+        match self {
+            PiiCategory::Email => "[EMAIL]",
+            PiiCategory::Phone => "[PHONE]",
+            PiiCategory::Iban => "[IBAN]",
+            PiiCategory::CreditCard => "[CREDIT_CARD]",
+            PiiCategory::Ipv4 => "[IPV4]",
+            PiiCategory::Ipv6 => "[IPV6]",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PiiCategoryRule {
+    category: PiiCategory,
+    /// Replacement template. Supports the placeholders `{first:N}` and `{last:N}` (the first/last
+    /// N characters of the match) and `{mask}` (the match with all letters and digits replaced by `*`).
+    #[serde(default)]
+    template: Option<String>,
+}
+
+impl PiiCategoryRule {
+    pub fn new(category: PiiCategory, template: Option<String>) -> PiiCategoryRule {
+        return PiiCategoryRule {
+            category,
+            template
+        };
+    }
+
+    pub fn category(&self) -> PiiCategory {
+        return self.category;
+    }
+    pub fn template(&self) -> &str {
+        return self.template.as_deref().unwrap_or(self.category.default_template());
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PiiRedaction {
+    /// The redacted categories. All categories with their default templates are redacted if empty.
+    #[serde(default)]
+    categories: Vec<PiiCategoryRule>,
+}
+
+impl PiiRedaction {
+    pub fn categories(&self) -> Vec<PiiCategoryRule> {
+        if self.categories.len() == 0 {
+            return PiiCategory::all().into_iter()
+                .map(|category| PiiCategoryRule::new(category, None))
+                .collect();
+        }
+        return self.categories.clone();
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CodeSnippetCleanup {
+    /// Regular expressions for prompts which are removed from the start of a line. Defaults to
+    /// shell (`$ `), Python (`>>> `, `... `) and PowerShell (`PS C:\> `) prompts. The root prompt
+    /// `# ` is not a default, it cannot be told apart from comments.
+    #[serde(default)]
+    prompts: Option<Vec<String>>,
+    /// Drops all lines without a prompt (i.e. command output), if any line has a prompt.
+    #[serde(default)]
+    drop_output: bool,
+    /// Removes line numbers in front of each line, if all non-empty lines are numbered consecutively.
+    #[serde(default = "default_true")]
+    line_numbers: bool,
+    /// Removes the leading whitespace common to all non-empty lines.
+    #[serde(default = "default_true")]
+    dedent: bool,
+}
+
+impl CodeSnippetCleanup {
+    pub fn prompts(&self) -> Vec<String> {
+        if let Some(prompts) = &self.prompts {
+            return prompts.clone();
+        }
+        return vec![
+            r"\$( |$)".to_string(),
+            r">>>( |$)".to_string(),
+            r"\.\.\.( |$)".to_string(),
+            r"PS [^>]*>( |$)".to_string(),
+        ];
+    }
+    pub fn drop_output(&self) -> bool {
+        return self.drop_output;
+    }
+    pub fn line_numbers(&self) -> bool {
+        return self.line_numbers;
+    }
+    pub fn dedent(&self) -> bool {
+        return self.dedent;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct HtmlToMarkdownConversion {
+    /// Drops images instead of converting them to `![alt](src)`.
+    #[serde(default)]
+    skip_images: bool,
+}
+
+impl HtmlToMarkdownConversion {
+    pub fn skip_images(&self) -> bool {
+        return self.skip_images;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct HtmlSanitization {
+    /// Tags which are kept. Other tags are removed, but their content is kept.
+    #[serde(default)]
+    allowed_tags: Option<Vec<String>>,
+    /// Attributes which are kept, by tag name. Attributes listed for `"*"` are kept on all tags.
+    #[serde(default)]
+    allowed_attributes: Option<HashMap<String, Vec<String>>>,
+    /// Tags which are removed including their content.
+    #[serde(default)]
+    removed_tags: Option<Vec<String>>,
+}
+
+impl HtmlSanitization {
+    pub fn allowed_tags(&self) -> Vec<String> {
+        if let Some(allowed_tags) = &self.allowed_tags {
+            return allowed_tags.clone();
+        }
+        return [
+            "a", "b", "strong", "i", "em", "u", "s", "del", "sub", "sup", "code", "kbd", "pre",
+            "p", "br", "hr", "h1", "h2", "h3", "h4", "h5", "h6", "ul", "ol", "li", "blockquote",
+            "table", "caption", "thead", "tbody", "tfoot", "tr", "th", "td", "img",
+        ].iter().map(|tag| tag.to_string()).collect();
+    }
+    pub fn allowed_attributes(&self) -> HashMap<String, Vec<String>> {
+        if let Some(allowed_attributes) = &self.allowed_attributes {
+            return allowed_attributes.clone();
+        }
+        return [
+            ("a", vec!["href", "title"]),
+            ("img", vec!["src", "alt", "title", "width", "height"]),
+            ("ol", vec!["start"]),
+            ("td", vec!["colspan", "rowspan"]),
+            ("th", vec!["colspan", "rowspan"]),
+        ].iter()
+            .map(|(tag, attributes)| (tag.to_string(), attributes.iter().map(|attribute| attribute.to_string()).collect()))
+            .collect();
+    }
+    pub fn removed_tags(&self) -> Vec<String> {
+        if let Some(removed_tags) = &self.removed_tags {
+            return removed_tags.clone();
+        }
+        return [
+            "head", "title", "meta", "link", "script", "style", "noscript", "template", "iframe",
+            "object", "embed", "form", "input", "button", "select", "textarea", "xml",
+        ].iter().map(|tag| tag.to_string()).collect();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TableFormat {
+    #[serde(rename = "markdown")]
+    Markdown,
+    #[serde(rename = "csv")]
+    Csv,
+    /// An array of objects with the header row as keys, or an array of arrays without header row.
+    #[serde(rename = "json")]
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TableHeader {
+    /// Uses the first row as header if it looks like one, i.e. its cells are distinct, non-empty
+    /// and not numeric.
+    #[serde(rename = "auto")]
+    Auto,
+    #[serde(rename = "present")]
+    Present,
+    #[serde(rename = "absent")]
+    Absent,
+}
+
+impl Default for TableHeader {
+    fn default() -> Self {
+        return TableHeader::Auto;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TableQuoting {
+    /// Quotes fields containing the delimiter, quotes or line breaks.
+    #[serde(rename = "necessary")]
+    Necessary,
+    #[serde(rename = "always")]
+    Always,
+    /// Quotes all fields which are not numbers.
+    #[serde(rename = "non_numeric")]
+    NonNumeric,
+    #[serde(rename = "never")]
+    Never,
+}
+
+impl Default for TableQuoting {
+    fn default() -> Self {
+        return TableQuoting::Necessary;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TableConversion {
+    format: TableFormat,
+    /// Delimiter of the input cells, defaults to tab.
+    #[serde(default)]
+    delimiter: Option<char>,
+    /// Whether quoted input cells (e.g. cells with line breaks) are unquoted.
+    #[serde(default = "default_true")]
+    unquote: bool,
+    #[serde(default)]
+    header: TableHeader,
+    /// Delimiter of the CSV output, defaults to `,`.
+    #[serde(default)]
+    output_delimiter: Option<char>,
+    /// Quoting of the CSV output.
+    #[serde(default)]
+    quoting: TableQuoting,
+    /// Quote character of the input and CSV output, defaults to `"`.
+    #[serde(default)]
+    quote: Option<char>,
+}
+
+impl TableConversion {
+    pub fn format(&self) -> TableFormat {
+        return self.format;
+    }
+    pub fn delimiter(&self) -> char {
+        return self.delimiter.unwrap_or('\t');
+    }
+    pub fn unquote(&self) -> bool {
+        return self.unquote;
+    }
+    pub fn header(&self) -> TableHeader {
+        return self.header;
+    }
+    pub fn output_delimiter(&self) -> char {
+        return self.output_delimiter.unwrap_or(',');
+    }
+    pub fn quoting(&self) -> TableQuoting {
+        return self.quoting;
+    }
+    pub fn quote(&self) -> char {
+        return self.quote.unwrap_or('"');
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DataFormat {
+    #[serde(rename = "json")]
+    Json,
+    #[serde(rename = "yaml")]
+    Yaml,
+    #[serde(rename = "xml")]
+    Xml,
+    #[serde(rename = "toml")]
+    Toml,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DataAction {
+    #[serde(rename = "pretty")]
+    Pretty,
+    #[serde(rename = "minify")]
+    Minify,
+    /// Leaves the text unchanged, but fails with the position of the first syntax error.
+    #[serde(rename = "validate")]
+    Validate,
+    /// Converts into the format given by `to`.
+    #[serde(rename = "convert")]
+    Convert,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StructuredDataTransformation {
+    format: DataFormat,
+    action: DataAction,
+    /// Target format of the `convert` action.
+    #[serde(default)]
+    to: Option<DataFormat>,
+    /// Number of spaces used for indentation by `pretty` and `convert`, defaults to 2.
+    #[serde(default)]
+    indent: Option<usize>,
+}
+
+impl StructuredDataTransformation {
+    pub fn format(&self) -> DataFormat {
+        return self.format;
+    }
+    pub fn action(&self) -> DataAction {
+        return self.action;
+    }
+    pub fn to(&self) -> Option<DataFormat> {
+        return self.to;
+    }
+    pub fn indent(&self) -> usize {
+        return self.indent.unwrap_or(2);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ByteEncoding {
+    #[serde(rename = "base64")]
+    Base64,
+    /// URL-safe Base64 alphabet (`-` and `_`) without padding.
+    #[serde(rename = "base64url")]
+    Base64Url,
+    #[serde(rename = "hex")]
+    Hex,
+    #[serde(rename = "quoted_printable")]
+    QuotedPrintable,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ByteEncodingTransformation {
+    scheme: ByteEncoding,
+    /// Character encoding of the bytes, defaults to `utf-8`.
+    #[serde(default)]
+    charset: Option<String>,
+}
+
+impl ByteEncodingTransformation {
+    pub fn scheme(&self) -> ByteEncoding {
+        return self.scheme;
+    }
+    pub fn charset(&self) -> &str {
+        return self.charset.as_ref().map(|charset| charset.as_str()).unwrap_or("utf-8");
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum FileListFormat {
+    /// Absolute paths, one per line.
+    #[serde(rename = "paths")]
+    Paths,
+    /// Absolute paths quoted for POSIX shells, separated by spaces.
+    #[serde(rename = "shell")]
+    Shell,
+    /// Paths relative to `base_dir`, one per line.
+    #[serde(rename = "relative")]
+    Relative,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileListConversion {
+    format: FileListFormat,
+    /// Directory the `relative` paths start from, relative paths are resolved against the
+    /// configuration file's directory.
+    #[serde(default)]
+    base_dir: Option<String>,
+}
+
+impl FileListConversion {
+    pub fn format(&self) -> FileListFormat {
+        return self.format;
+    }
+    pub fn base_dir(&self) -> &Option<String> {
+        return &self.base_dir;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MojibakeRepair {
+    /// Single-byte encodings the UTF-8 text may have been wrongly decoded with, defaults to
+    /// `windows-1252` (which includes Latin-1).
+    #[serde(default)]
+    encodings: Option<Vec<String>>,
+    /// How often the text may have been wrongly decoded, defaults to 2.
+    #[serde(default)]
+    max_rounds: Option<usize>,
+}
+
+impl MojibakeRepair {
+    pub fn encodings(&self) -> &Option<Vec<String>> {
+        return &self.encodings;
+    }
+    pub fn max_rounds(&self) -> Option<usize> {
+        return self.max_rounds;
+    }
+}
+
+fn default_true() -> bool {
+    return true;
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomTransformation {
+    #[serde(rename = "type")]
+    type_name: String,
+    /// Options passed unmodified to the registered factory.
+    #[serde(default)]
+    options: serde_yaml::Value,
+}
+
+impl CustomTransformation {
+    pub fn type_name(&self) -> &str {
+        return &self.type_name;
+    }
+    pub fn options(&self) -> &serde_yaml::Value {
+        return &self.options;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum UnencodableAction {
+    /// Publishing fails with an error naming the first character which cannot be encoded.
+    #[serde(rename = "fail")]
+    Fail,
+    /// Replaces the character with `?`.
+    #[serde(rename = "replace")]
+    Replace,
+    /// Replaces the character with its ASCII transliteration, e.g. `€` with `EUR`, or `?`.
+    #[serde(rename = "transliterate")]
+    Transliterate,
+    /// Replaces the character with a replacement pattern like in character transformations,
+    /// e.g. `{entity}` or `{uni-esc}`.
+    #[serde(rename = "escape")]
+    Escape(String),
+}
+
+impl Default for UnencodableAction {
+    fn default() -> Self {
+        return UnencodableAction::Transliterate;
+    }
+}
+
+/// A clipboard target the cleaned text is published under, e.g. `UTF8_STRING` or `text/html`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutputTarget {
+    /// The target atom. The charset is derived from it: `STRING` is Latin-1, `text/...` targets
+    /// use their `charset` parameter (default UTF-8).
+    target: String,
+    /// Overrides the charset derived from the target, e.g. `utf-16le`.
+    #[serde(default)]
+    charset: Option<String>,
+    /// Writes a byte order mark before UTF-8, UTF-16LE and UTF-16BE text.
+    #[serde(default)]
+    bom: bool,
+    /// How characters the charset cannot encode are handled, defaults to `transliterate`.
+    #[serde(default)]
+    unencodable: UnencodableAction,
+}
+
+impl OutputTarget {
+    pub fn new(target: &str) -> OutputTarget {
+        return OutputTarget {
+            target: target.to_string(),
+            charset: None,
+            bom: false,
+            unencodable: UnencodableAction::default(),
+        };
+    }
+
+    pub fn target(&self) -> &str {
+        return &self.target;
+    }
+    pub fn charset(&self) -> &Option<String> {
+        return &self.charset;
+    }
+    pub fn bom(&self) -> bool {
+        return self.bom;
+    }
+    pub fn unencodable(&self) -> &UnencodableAction {
+        return &self.unencodable;
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TransformationProfile {
     name: String,
     display_name: Option<String>,
     description: Option<String>,
+    /// Name of a profile whose transformations are inherited.
+    #[serde(default)]
+    extends: Option<String>,
+    /// Replacements for single inherited transformations of the `extends` profile.
+    #[serde(default)]
+    overrides: Vec<TransformationOverride>,
+    /// Profiles whose transformations are executed before or after this profile's transformations.
+    #[serde(default)]
+    include: ProfileIncludes,
+    /// Clipboard target (e.g. `text/html`) whose content is transformed instead of the plain text.
+    #[serde(default)]
+    source_target: Option<String>,
+    /// Sanitizes the `text/html` target instead of cleaning the plain text. The transformations are
+    /// applied to the text nodes only, the cleaned HTML is published together with a plain text target.
+    #[serde(default)]
+    html_sanitizer: Option<HtmlSanitization>,
+    /// Clipboard targets the cleaned text is published under. If not set, the text is published
+    /// as GTK's default text targets.
+    #[serde(default)]
+    output_targets: Option<Vec<OutputTarget>>,
+    /// Re-serves the original data of all targets which are not replaced by the cleaned text, e.g.
+    /// images or file lists.
+    #[serde(default)]
+    preserve_targets: bool,
+    #[serde(default)]
     transformations: Vec<Transformation>,
 }
 
@@ -165,6 +1131,27 @@ impl TransformationProfile {
     pub fn description(&self) -> &Option<String> {
         return &self.description;
     }
+    pub fn extends(&self) -> &Option<String> {
+        return &self.extends;
+    }
+    pub fn overrides(&self) -> &Vec<TransformationOverride> {
+        return &self.overrides;
+    }
+    pub fn include(&self) -> &ProfileIncludes {
+        return &self.include;
+    }
+    pub fn source_target(&self) -> &Option<String> {
+        return &self.source_target;
+    }
+    pub fn html_sanitizer(&self) -> &Option<HtmlSanitization> {
+        return &self.html_sanitizer;
+    }
+    pub fn output_targets(&self) -> &Option<Vec<OutputTarget>> {
+        return &self.output_targets;
+    }
+    pub fn preserve_targets(&self) -> bool {
+        return self.preserve_targets;
+    }
     pub fn transformations(&self) -> &Vec<Transformation> {
         return &self.transformations;
     }
@@ -174,7 +1161,131 @@ impl TransformationProfile {
             name: "identity".to_string(),
             display_name: Some("Identity transformation".to_string()),
             description: Some("Does not change the text.".to_string()),
+            extends: None,
+            overrides: Vec::new(),
+            include: ProfileIncludes::default(),
+            source_target: None,
+            html_sanitizer: None,
+            output_targets: None,
+            preserve_targets: false,
             transformations: Vec::new()
         };
     }
 }
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct ProfileIncludes {
+    #[serde(default)]
+    before: Vec<String>,
+    #[serde(default)]
+    after: Vec<String>,
+}
+
+impl ProfileIncludes {
+    pub fn before(&self) -> &Vec<String> {
+        return &self.before;
+    }
+    pub fn after(&self) -> &Vec<String> {
+        return &self.after;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransformationOverride {
+    /// Zero-based index of the inherited transformation that is replaced.
+    index: usize,
+    transformation: Transformation,
+}
+
+impl TransformationOverride {
+    pub fn index(&self) -> usize {
+        return self.index;
+    }
+    pub fn transformation(&self) -> &Transformation {
+        return &self.transformation;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(yaml: &str) -> Result<Transformation, String> {
+        return serde_yaml::from_str::<Transformation>(yaml).map_err(|err| err.to_string());
+    }
+
+    #[test]
+    fn parses_typed_and_character_transformations() {
+        assert_eq!(parse("emoji: { mode: shortcode }").unwrap(),
+                   Transformation::Emoji { emoji: EmojiTransformation::new(EmojiMode::Shortcode) });
+        let character = parse("filters: [ { ref: C0 } ]\naction: remove").unwrap();
+        assert_eq!(character, Transformation::Character(CharacterTransformation::new(
+            vec![WrappedFilter::Reference("C0".to_string())], TransformationAction::Remove)));
+    }
+
+    #[test]
+    fn errors_name_the_type_and_field() {
+        let err = parse("emoji: { mdoe: remove }").unwrap_err();
+        assert!(err.contains("Invalid \"emoji\" transformation") && err.contains("mode"), "{}", err);
+        let err = parse("emoji: { mode: shout }").unwrap_err();
+        assert!(err.contains("shout"), "{}", err);
+        let err = parse("emojj: { mode: remove }").unwrap_err();
+        assert!(err.contains("Unknown transformation type \"emojj\""), "{}", err);
+        let err = parse("{ emoji: { mode: remove }, table: { format: csv } }").unwrap_err();
+        assert!(err.contains("exactly one type key, found emoji, table"), "{}", err);
+    }
+
+    fn composed_config() -> Config {
+        return serde_yaml::from_str(r#"
+filters: {}
+profiles:
+  - name: "base"
+    transformations: [ { emoji: { mode: remove } }, { emoji: { mode: name } } ]
+  - name: "first"
+    transformations: [ { mask_secrets: { mode: full } } ]
+  - name: "last"
+    transformations: [ { repair_mojibake: {} } ]
+  - name: "child"
+    extends: "base"
+    overrides: [ { index: 1, transformation: { emoji: { mode: shortcode } } } ]
+    include: { before: [ "first" ], after: [ "last" ] }
+    transformations: [ { code_snippet: {} } ]
+  - name: "self"
+    include: { before: [ "self" ] }
+  - name: "ping"
+    extends: "pong"
+  - name: "pong"
+    include: { after: [ "ping" ] }
+  - name: "orphan"
+    extends: "missing"
+"#).unwrap();
+    }
+
+    fn resolve(config: &Config, name: &str) -> anyhow::Result<Vec<Transformation>> {
+        return config.resolve_transformations(config.profile(name).unwrap());
+    }
+
+    #[test]
+    fn resolves_includes_around_overridden_parent_steps() {
+        let config = composed_config();
+        let expected = [
+            "mask_secrets: { mode: full }",
+            "emoji: { mode: remove }",
+            "emoji: { mode: shortcode }",
+            "code_snippet: {}",
+            "repair_mojibake: {}",
+        ].iter().map(|yaml| parse(yaml).unwrap()).collect::<Vec<_>>();
+        assert_eq!(resolve(&config, "child").unwrap(), expected);
+    }
+
+    #[test]
+    fn rejects_cyclic_and_unknown_profile_references() {
+        let config = composed_config();
+        let err = resolve(&config, "self").unwrap_err().to_string();
+        assert_eq!(err, "Cyclic profile composition: self -> self");
+        let err = resolve(&config, "ping").unwrap_err().to_string();
+        assert_eq!(err, "Cyclic profile composition: ping -> pong -> ping");
+        let err = resolve(&config, "orphan").unwrap_err().to_string();
+        assert_eq!(err, "Profile \"orphan\" extends unknown profile \"missing\".");
+    }
+}
@@ -108,6 +108,11 @@ impl ConfigurationLoader {
     }
 
     pub fn load_configuration<T: DeserializeOwned>(&self) -> anyhow::Result<T> {
+        return self.load_configuration_with_path().map(|(result, _path)| result);
+    }
+
+    /// Loads the configuration and returns it together with the path of the configuration file.
+    pub fn load_configuration_with_path<T: DeserializeOwned>(&self) -> anyhow::Result<(T, PathBuf)> {
         for search_path in self.search_paths.iter().rev() {
             match self.load_configuration_for_path(search_path.as_str()) {
                 Ok(result) => {
@@ -121,7 +126,7 @@ impl ConfigurationLoader {
         return Err(anyhow::Error::msg("No configuration file found."));
     }
 
-    fn load_configuration_for_path<T: DeserializeOwned>(&self, path: &str) -> anyhow::Result<T> {
+    fn load_configuration_for_path<T: DeserializeOwned>(&self, path: &str) -> anyhow::Result<(T, PathBuf)> {
         println!("looking for config file in path: {}", path);
         for suffix in &self.file_suffixes {
             let result = self.load_configuration_for_suffix(path, suffix.as_str());
@@ -137,14 +142,15 @@ impl ConfigurationLoader {
         return Err(anyhow::Error::msg(format!("No configuration file found for path \"{}\"", path)));
     }
 
-    fn load_configuration_for_suffix<T: DeserializeOwned>(&self, path: &str, suffix: &str) -> anyhow::Result<T> {
+    fn load_configuration_for_suffix<T: DeserializeOwned>(&self, path: &str, suffix: &str) -> anyhow::Result<(T, PathBuf)> {
         let filename = format!("{}{}",
                                self.base_name,
                                suffix
         );
 
         let path = std::path::PathBuf::new().join(path).join(filename);
-        return Self::try_load_single_configuration(&path, suffix);
+        let result = Self::try_load_single_configuration(&path, suffix)?;
+        return Ok((result, path));
     }
 
     fn try_load_single_configuration<T: DeserializeOwned>(path: &PathBuf, suffix: &str) -> anyhow::Result<T> {
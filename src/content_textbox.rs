@@ -1,10 +1,14 @@
+use std::cell::Cell;
 use std::sync::Arc;
-use gtk::{Application, TextBuffer, TextView};
-use gtk::prelude::{TextBufferExt, TextViewExt};
+use gtk::{Application, TextBuffer, TextTag, TextView};
+use gtk::prelude::{TextBufferExt, TextMarkExt, TextTagTableExt, TextViewExt};
+use parking_lot::RwLock;
 
 pub struct ContentTextbox {
     app: Arc<Application>,
     textbox: Arc<TextView>,
+
+    on_selection_handler: RwLock<Cell<Box<dyn Fn(&ContentTextbox, usize, usize) -> () + 'static>>>,
 }
 
 impl ContentTextbox {
@@ -12,10 +16,32 @@ impl ContentTextbox {
         let result = Arc::new(ContentTextbox {
             app: app.clone(),
             textbox: textbox.clone(),
+            on_selection_handler: RwLock::new(Cell::new(Box::new(|_, _, _| {}))),
         });
 
         textbox.set_editable(false);
 
+        if let Some(buffer) = textbox.buffer() {
+            let result_clone = result.clone();
+            buffer.connect_mark_set(move |buffer, _iter, mark| {
+                let result_clone = result_clone.clone();
+                let mark_name = mark.name().map(|name| name.to_string()).unwrap_or(String::new());
+                if mark_name != "insert" && mark_name != "selection_bound" {
+                    return;
+                }
+                // without selection, the character after the cursor is selected
+                let (start, end) = if let Some((start, end)) = buffer.selection_bounds() {
+                    (start.offset() as usize, end.offset() as usize)
+                } else if let Some(insert) = buffer.get_insert() {
+                    let offset = buffer.iter_at_mark(&insert).offset() as usize;
+                    (offset, offset + 1)
+                } else {
+                    return;
+                };
+                result_clone.fire_selection(start, end);
+            });
+        }
+
         return result.clone();
     }
 
@@ -41,6 +67,53 @@ impl ContentTextbox {
         return None;
     }
 
+    /// Highlights the given character ranges (start inclusive, end exclusive) with the tag
+    /// `tag_name`. The tag is created with the given background color if it does not exist yet.
+    pub fn highlight(&self, tag_name: &str, background: &str, ranges: &[(usize, usize)]) {
+        let buffer = self.textbox.buffer();
+        if let Some(buffer) = buffer {
+            if let Some(tag_table) = buffer.tag_table() {
+                if tag_table.lookup(tag_name).is_none() {
+                    let tag = TextTag::builder()
+                        .name(tag_name)
+                        .background(background)
+                        .build();
+                    tag_table.add(&tag);
+                }
+            }
+            for (start, end) in ranges.iter() {
+                let start = buffer.iter_at_offset(*start as i32);
+                let end = buffer.iter_at_offset(*end as i32);
+                buffer.apply_tag_by_name(tag_name, &start, &end);
+            }
+        }
+    }
+
+    pub fn clear_highlight(&self, tag_name: &str) {
+        let buffer = self.textbox.buffer();
+        if let Some(buffer) = buffer {
+            buffer.remove_tag_by_name(tag_name, &buffer.start_iter(), &buffer.end_iter());
+        }
+    }
+
+    /// Scrolls the textbox so that the character at the given offset is visible.
+    pub fn scroll_to_offset(&self, offset: usize) {
+        let buffer = self.textbox.buffer();
+        if let Some(buffer) = buffer {
+            let mut iter = buffer.iter_at_offset(offset as i32);
+            self.textbox.scroll_to_iter(&mut iter, 0.0, false, 0.0, 0.0);
+        }
+    }
+
+    fn fire_selection(&self, start: usize, end: usize) {
+        self.on_selection_handler.write().get_mut()(self, start, end);
+    }
+
+    /// Sets the handler called with the selected character range (start inclusive, end exclusive).
+    pub fn on_selection<F: Fn(&ContentTextbox, usize, usize) -> () + 'static>(&self, handler: F) {
+        self.on_selection_handler.write().set(Box::new(handler));
+    }
+
     pub fn set_content(&self, content: &str) {
         let buffer = self.textbox.buffer();
         if let Some(buffer) = buffer {
@@ -0,0 +1,119 @@
+use crate::config::{EmojiMode, EmojiTransformation};
+use crate::transformer::Transformer;
+use serde::{ Serialize, Deserialize };
+use unicode_segmentation::UnicodeSegmentation;
+
+const ZERO_WIDTH_JOINER: char = '\u{200d}';
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmojiTransformer {
+    mode: EmojiMode,
+}
+
+impl EmojiTransformer {
+    pub fn new(mode: EmojiMode) -> EmojiTransformer {
+        return EmojiTransformer {
+            mode
+        };
+    }
+
+    pub fn from_config(trafo_config: &EmojiTransformation) -> anyhow::Result<EmojiTransformer> {
+        return Ok(EmojiTransformer::new(trafo_config.mode().clone()));
+    }
+
+    fn replace(&self, emoji: &emojis::Emoji) -> String {
+        match self.mode {
+            EmojiMode::Remove => {
+                return String::new();
+            }
+            EmojiMode::Shortcode => {
+                if let Some(shortcode) = emoji.shortcode() {
+                    return format!(":{}:", shortcode);
+                }
+                // not every emoji has a GitHub shortcode, so fall back to the CLDR name.
+                return format!("[{}]", emoji.name());
+            }
+            EmojiMode::Name => {
+                return format!("[{}]", emoji.name());
+            }
+        }
+    }
+}
+
+impl Transformer for EmojiTransformer {
+    fn execute(&self, text: &str) -> anyhow::Result<String> {
+        let mut output = String::new();
+        // ZWJ sequences, skin tones, flags and keycaps are all single extended grapheme clusters.
+        for grapheme in text.graphemes(true) {
+            if let Some(emoji) = lookup_emoji(grapheme) {
+                output += self.replace(emoji).as_str();
+                continue;
+            }
+
+            // unknown ZWJ sequences (e.g. from a newer unicode version than the emoji database)
+            // are mapped component-wise, as long as all components are known emoji.
+            if grapheme.contains(ZERO_WIDTH_JOINER) {
+                let components: Vec<Option<&'static emojis::Emoji>> = grapheme.split(ZERO_WIDTH_JOINER)
+                    .map(|component| lookup_emoji(component))
+                    .collect();
+                if components.iter().all(|component| component.is_some()) {
+                    for component in components {
+                        output += self.replace(component.unwrap()).as_str();
+                    }
+                    continue;
+                }
+            }
+
+            output += grapheme;
+        }
+        return Ok(output);
+    }
+}
+
+/// Looks up an emoji sequence. Single characters which are only emoji when followed by the
+/// variation selector U+FE0F (e.g. `©` or `™`) use text presentation by default and are
+/// therefore not treated as emoji.
+fn lookup_emoji(text: &str) -> Option<&'static emojis::Emoji> {
+    let emoji = emojis::get(text)?;
+    if text.chars().count() == 1 && emoji.as_str() != text {
+        return None;
+    }
+    return Some(emoji);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transform(mode: EmojiMode, text: &str) -> String {
+        return EmojiTransformer::new(mode).execute(text).unwrap();
+    }
+
+    #[test]
+    fn replaces_zwj_sequences_as_a_whole() {
+        let family = "👨\u{200d}👩\u{200d}👧";
+        assert_eq!(transform(EmojiMode::Remove, format!("a{}b", family).as_str()), "ab");
+        assert_eq!(transform(EmojiMode::Name, family), "[family: man, woman, girl]");
+    }
+
+    #[test]
+    fn keeps_skin_tone_modifiers_with_their_emoji() {
+        assert_eq!(transform(EmojiMode::Name, "👍🏽"), "[thumbs up: medium skin tone]");
+        assert_eq!(transform(EmojiMode::Remove, "ok 👍🏽!"), "ok !");
+    }
+
+    #[test]
+    fn replaces_flags_and_keycaps() {
+        assert_eq!(transform(EmojiMode::Shortcode, "🇩🇪"), ":de:");
+        assert_eq!(transform(EmojiMode::Name, "🇩🇪"), "[flag: Germany]");
+        assert_eq!(transform(EmojiMode::Name, "1\u{fe0f}\u{20e3}"), "[keycap: 1]");
+        assert_eq!(transform(EmojiMode::Remove, "#1 1\u{fe0f}\u{20e3}"), "#1 ");
+    }
+
+    #[test]
+    fn keeps_symbols_in_text_presentation() {
+        assert_eq!(transform(EmojiMode::Remove, "© 2024"), "© 2024");
+        assert_eq!(transform(EmojiMode::Remove, "©\u{fe0f} 2024"), " 2024");
+        assert_eq!(transform(EmojiMode::Shortcode, "©\u{fe0f}"), ":copyright:");
+    }
+}
@@ -1,18 +1,34 @@
 use std::cell::Cell;
 use std::sync::Arc;
-use gtk::{Application, ComboBox};
-use gtk::prelude::ComboBoxExt;
+use gtk::{Application, ComboBoxText};
+use gtk::prelude::{ComboBoxExt, ComboBoxTextExt};
 use parking_lot::RwLock;
+use clipboard_cleaner::charset;
+
+/// Decoders for targets which are not plain text, listed after the text encodings.
+const DECODERS: [(&str, &str); 2] = [
+    ("rtf", "RTF (decoded to text)"),
+    ("uri-list", "File list (decoded URIs)"),
+];
 
 pub struct EncodingDropdown {
     app: Arc<Application>,
-    dropdown: Arc<ComboBox>,
+    dropdown: Arc<ComboBoxText>,
 
     on_change_handler: RwLock<Cell<Box<dyn Fn(&EncodingDropdown, Option<String>) -> () + 'static>>>,
 }
 
 impl EncodingDropdown {
-    pub fn new(app: Arc<Application>, dropdown: Arc<ComboBox>) -> Arc<EncodingDropdown> {
+    pub fn new(app: Arc<Application>, dropdown: Arc<ComboBoxText>) -> Arc<EncodingDropdown> {
+        dropdown.append(Some("-"), "-");
+        for (id, name) in charset::encodings() {
+            dropdown.append(Some(id.as_str()), name.as_str());
+        }
+        for (id, name) in DECODERS.iter() {
+            dropdown.append(Some(id), name);
+        }
+        dropdown.set_active_id(Some("-"));
+
         let result = Arc::new(EncodingDropdown {
             app: app.clone(),
             dropdown: dropdown.clone(),
@@ -39,8 +55,12 @@ impl EncodingDropdown {
     }
 
     pub fn set_encoding(&self, encoding: Option<&str>) {
+        // aliases like "cp1252" select their encoding
+        let encoding = encoding.map(|encoding| charset::canonical_name(encoding).unwrap_or(encoding.to_string()));
         if let Some(encoding) = encoding {
-            self.dropdown.set_active_id(Some(encoding));
+            if !self.dropdown.set_active_id(Some(encoding.as_str())) {
+                self.dropdown.set_active_id(Some("-"));
+            }
         } else {
             self.dropdown.set_active_id(Some("-"));
         }
@@ -0,0 +1,170 @@
+use std::path::{Component, Path, PathBuf};
+use crate::config::{Config, FileListConversion, FileListFormat};
+use crate::transformer::Transformer;
+
+/// Converts the `file://` URIs of copied files into absolute paths, shell arguments or paths
+/// relative to a base directory.
+#[derive(Debug, Clone)]
+pub struct FileListConverter {
+    format: FileListFormat,
+    base_dir: Option<PathBuf>,
+}
+
+impl FileListConverter {
+    pub fn from_config(config: &Config, trafo_config: &FileListConversion) -> anyhow::Result<FileListConverter> {
+        let base_dir = trafo_config.base_dir().as_ref().map(|dir| config.resolve_path(dir.as_str()));
+        if trafo_config.format() == FileListFormat::Relative && base_dir.is_none() {
+            return Err(anyhow::Error::msg("The relative file list format needs a base directory (\"base_dir\")."));
+        }
+        return Ok(FileListConverter {
+            format: trafo_config.format(),
+            base_dir,
+        });
+    }
+}
+
+impl Transformer for FileListConverter {
+    fn execute(&self, text: &str) -> anyhow::Result<String> {
+        let paths = file_paths(text);
+        match self.format {
+            FileListFormat::Paths => return Ok(paths.join("\n")),
+            FileListFormat::Shell => {
+                return Ok(paths.iter().map(|path| shell_quote(path.as_str())).collect::<Vec<String>>().join(" "));
+            }
+            FileListFormat::Relative => {
+                let base_dir = self.base_dir.as_ref().unwrap();
+                return Ok(paths.iter()
+                    .map(|path| if path.starts_with('/') {
+                        relative_path(Path::new(path.as_str()), base_dir.as_path())
+                    } else {
+                        path.clone()
+                    })
+                    .collect::<Vec<String>>()
+                    .join("\n"));
+            }
+        }
+    }
+}
+
+/// Returns the entries of a `text/uri-list` or `x-special/gnome-copied-files` list. Local `file://`
+/// URIs are decoded into paths, other URIs and lines which are already paths are kept.
+pub fn file_paths(text: &str) -> Vec<String> {
+    let mut paths: Vec<String> = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        // comments are allowed by RFC 2483, GNOME starts the list with the action
+        if line.len() == 0 || line.starts_with('#') || (index == 0 && (line == "copy" || line == "cut")) {
+            continue;
+        }
+        paths.push(file_uri_to_path(line).unwrap_or(line.to_string()));
+    }
+    return paths;
+}
+
+/// Decodes `file:///path`, `file://localhost/path` and `file:/path`. URIs of other hosts are not local paths.
+fn file_uri_to_path(uri: &str) -> Option<String> {
+    let rest = uri.strip_prefix("file:")?;
+    let path = if let Some(authority) = rest.strip_prefix("//") {
+        let slash = authority.find('/')?;
+        let host = &authority[..slash];
+        if host.len() > 0 && host != "localhost" {
+            return None;
+        }
+        &authority[slash..]
+    } else {
+        rest
+    };
+    return Some(percent_decode(path));
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut data: Vec<u8> = Vec::new();
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%' {
+            let hex = bytes.get(index + 1..index + 3)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            if let Some(byte) = hex {
+                data.push(byte);
+                index += 3;
+                continue;
+            }
+        }
+        data.push(bytes[index]);
+        index += 1;
+    }
+    return String::from_utf8_lossy(data.as_slice()).to_string();
+}
+
+/// Quotes an argument for POSIX shells if it contains characters other than `[A-Za-z0-9_@%+=:,./-]`.
+fn shell_quote(argument: &str) -> String {
+    let is_safe = argument.len() > 0 && argument.chars()
+        .all(|ch| ch.is_ascii_alphanumeric() || "_@%+=:,./-".contains(ch));
+    if is_safe {
+        return argument.to_string();
+    }
+    return format!("'{}'", argument.replace('\'', "'\\''"));
+}
+
+/// Computes the path from `base_dir` to `path` without accessing the file system.
+fn relative_path(path: &Path, base_dir: &Path) -> String {
+    let path = normalize(path);
+    let base_dir = normalize(base_dir);
+    let common = path.iter().zip(base_dir.iter())
+        .take_while(|(component, base_component)| component == base_component)
+        .count();
+    let mut relative: Vec<String> = vec!["..".to_string(); base_dir.len() - common];
+    relative.extend(path[common..].iter().cloned());
+    if relative.len() == 0 {
+        return ".".to_string();
+    }
+    return relative.join("/");
+}
+
+fn normalize(path: &Path) -> Vec<String> {
+    let mut components: Vec<String> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => components.push(name.to_string_lossy().to_string()),
+            Component::ParentDir => {
+                components.pop();
+            }
+            _ => {}
+        }
+    }
+    return components;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COPIED_FILES: &str = "copy\nfile:///home/me/proj/a%20b.txt\r\nfile://localhost/home/me/it's%C3%A4.md\n\
+        # comment\nfile://other/x\n/tmp/plain\n";
+
+    fn convert(yaml: &str, text: &str) -> anyhow::Result<String> {
+        let config = Config::default();
+        let trafo_config: FileListConversion = serde_yaml::from_str(yaml).unwrap();
+        return FileListConverter::from_config(&config, &trafo_config)?.execute(text);
+    }
+
+    #[test]
+    fn decodes_file_uris() {
+        assert_eq!(file_paths(COPIED_FILES), vec!["/home/me/proj/a b.txt", "/home/me/it'sä.md", "file://other/x", "/tmp/plain"]);
+    }
+
+    #[test]
+    fn converts_to_shell_arguments() {
+        assert_eq!(convert("{ format: shell }", COPIED_FILES).unwrap(),
+                   "'/home/me/proj/a b.txt' '/home/me/it'\\''sä.md' file://other/x /tmp/plain");
+    }
+
+    #[test]
+    fn converts_to_relative_paths() {
+        assert_eq!(convert("{ format: relative, base_dir: /home/me/proj/sub }", COPIED_FILES).unwrap(),
+                   "../a b.txt\n../../it'sä.md\nfile://other/x\n../../../../tmp/plain");
+        assert!(convert("{ format: relative }", COPIED_FILES).is_err());
+    }
+}
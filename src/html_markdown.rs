@@ -0,0 +1,293 @@
+use ego_tree::NodeRef;
+use regex::Regex;
+use scraper::{Html, Node};
+use crate::config::HtmlToMarkdownConversion;
+use crate::transformer::Transformer;
+
+/// Converts HTML (e.g. the `text/html` clipboard target) into GitHub flavored Markdown.
+#[derive(Debug, Clone)]
+pub struct HtmlToMarkdownConverter {
+    skip_images: bool,
+    blank_lines: Regex,
+    whitespace: Regex,
+}
+
+impl HtmlToMarkdownConverter {
+    pub fn from_config(trafo_config: &HtmlToMarkdownConversion) -> anyhow::Result<HtmlToMarkdownConverter> {
+        return Ok(HtmlToMarkdownConverter {
+            skip_images: trafo_config.skip_images(),
+            blank_lines: Regex::new(r"\n[ \t]*\n(\s*\n)+")?,
+            whitespace: Regex::new(r"\s+")?,
+        });
+    }
+
+    pub fn convert(&self, html: &str) -> String {
+        let document = Html::parse_document(html);
+        let markdown = self.render_children(document.tree.root());
+        return self.blank_lines.replace_all(markdown.as_str(), "\n\n").trim().to_string() + "\n";
+    }
+
+    fn render_children(&self, node: NodeRef<Node>) -> String {
+        let mut output = String::new();
+        for child in node.children() {
+            output += self.render(child).as_str();
+        }
+        return output;
+    }
+
+    fn render(&self, node: NodeRef<Node>) -> String {
+        match node.value() {
+            Node::Text(text) => {
+                let text = self.whitespace.replace_all(&text[..], " ");
+                return escape_markdown(text.as_ref());
+            }
+            Node::Element(element) => {
+                let name = element.name().to_lowercase();
+                match name.as_str() {
+                    "head" | "script" | "style" | "template" | "noscript" => {
+                        return String::new();
+                    }
+                    "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                        let level = name[1..].parse::<usize>().unwrap_or(1);
+                        let content = self.render_inline(node);
+                        return block(format!("{} {}", "#".repeat(level), content).as_str());
+                    }
+                    "p" | "div" | "section" | "article" | "header" | "footer" | "main" | "figure" => {
+                        return block(self.render_children(node).as_str());
+                    }
+                    "br" => {
+                        return "  \n".to_string();
+                    }
+                    "hr" => {
+                        return block("---");
+                    }
+                    "strong" | "b" => {
+                        return wrap_inline("**", self.render_children(node).as_str());
+                    }
+                    "em" | "i" => {
+                        return wrap_inline("*", self.render_children(node).as_str());
+                    }
+                    "del" | "s" | "strike" => {
+                        return wrap_inline("~~", self.render_children(node).as_str());
+                    }
+                    "code" | "kbd" | "samp" | "tt" => {
+                        let code = text_content(node);
+                        let fence = if code.contains('`') { "``" } else { "`" };
+                        return format!("{}{}{}", fence, code, fence);
+                    }
+                    "a" => {
+                        let content = self.render_children(node);
+                        if let Some(href) = element.attr("href") {
+                            if content.trim().len() == 0 {
+                                return format!("<{}>", href);
+                            }
+                            return format!("[{}]({})", content.trim(), href.replace(' ', "%20"));
+                        }
+                        return content;
+                    }
+                    "img" => {
+                        if self.skip_images {
+                            return String::new();
+                        }
+                        let alt = element.attr("alt").unwrap_or("");
+                        let src = element.attr("src").unwrap_or("");
+                        return format!("![{}]({})", escape_markdown(alt), src.replace(' ', "%20"));
+                    }
+                    "pre" => {
+                        let language = node.descendants()
+                            .filter_map(|descendant| descendant.value().as_element())
+                            .flat_map(|element| element.classes())
+                            .find_map(|class| class.strip_prefix("language-").or_else(|| class.strip_prefix("lang-")))
+                            .unwrap_or("");
+                        let code = text_content(node);
+                        let fence = if code.contains("```") { "~~~" } else { "```" };
+                        return format!("\n\n{}{}\n{}\n{}\n\n", fence, language, code.trim_end_matches('\n'), fence);
+                    }
+                    "blockquote" => {
+                        let content = self.render_children(node);
+                        let content = self.blank_lines.replace_all(content.trim(), "\n\n");
+                        let quoted = content.lines()
+                            .map(|line| if line.len() > 0 { format!("> {}", line) } else { ">".to_string() })
+                            .collect::<Vec<String>>()
+                            .join("\n");
+                        return block(quoted.as_str());
+                    }
+                    "ul" | "ol" => {
+                        return block(self.render_list(node, name.as_str() == "ol").as_str());
+                    }
+                    "table" => {
+                        return block(self.render_table(node).as_str());
+                    }
+                    _ => {
+                        return self.render_children(node);
+                    }
+                }
+            }
+            Node::Document | Node::Fragment => {
+                return self.render_children(node);
+            }
+            _ => {
+                return String::new();
+            }
+        }
+    }
+
+    /// Renders the content of an element on a single line.
+    fn render_inline(&self, node: NodeRef<Node>) -> String {
+        let content = self.render_children(node);
+        return self.whitespace.replace_all(content.as_str(), " ").trim().to_string();
+    }
+
+    fn render_list(&self, node: NodeRef<Node>, ordered: bool) -> String {
+        let start = node.value().as_element()
+            .and_then(|element| element.attr("start"))
+            .and_then(|start| start.parse::<usize>().ok())
+            .unwrap_or(1);
+        let mut items: Vec<String> = Vec::new();
+        for child in node.children() {
+            let is_item = child.value().as_element().map(|element| element.name() == "li").unwrap_or(false);
+            if !is_item {
+                continue;
+            }
+            let marker = if ordered { format!("{}. ", start + items.len()) } else { "- ".to_string() };
+            let indentation = " ".repeat(marker.len());
+            // list items are rendered as tight list, nested blocks only keep single line breaks
+            let content = self.render_children(child);
+            let content = self.blank_lines.replace_all(content.trim(), "\n");
+            let content = content.replace("\n\n", "\n");
+            let mut item = String::new();
+            for (index, line) in content.lines().enumerate() {
+                if index == 0 {
+                    item += marker.as_str();
+                } else {
+                    item += "\n";
+                    item += indentation.as_str();
+                }
+                item += line;
+            }
+            if item.len() == 0 {
+                item = marker.trim_end().to_string();
+            }
+            items.push(item);
+        }
+        return items.join("\n");
+    }
+
+    fn render_table(&self, node: NodeRef<Node>) -> String {
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        for row in node.descendants() {
+            let is_row = row.value().as_element().map(|element| element.name() == "tr").unwrap_or(false);
+            if !is_row {
+                continue;
+            }
+            let mut cells: Vec<String> = Vec::new();
+            for cell in row.children() {
+                if let Some(element) = cell.value().as_element() {
+                    if element.name() == "td" || element.name() == "th" {
+                        cells.push(self.render_inline(cell).replace('|', "\\|"));
+                    }
+                }
+            }
+            if cells.len() > 0 {
+                rows.push(cells);
+            }
+        }
+        if rows.len() == 0 {
+            return String::new();
+        }
+
+        // markdown tables have exactly one header row, which is always the first row.
+        let columns = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        let mut output = String::new();
+        for (index, row) in rows.iter().enumerate() {
+            let mut cells = row.clone();
+            cells.resize(columns, String::new());
+            output += format!("| {} |\n", cells.join(" | ")).as_str();
+            if index == 0 {
+                output += format!("|{}\n", " --- |".repeat(columns)).as_str();
+            }
+        }
+        return output;
+    }
+}
+
+impl Transformer for HtmlToMarkdownConverter {
+    fn execute(&self, text: &str) -> anyhow::Result<String> {
+        return Ok(self.convert(text));
+    }
+}
+
+fn block(content: &str) -> String {
+    let content = content.trim().replace("  \n ", "  \n");
+    return format!("\n\n{}\n\n", content);
+}
+
+/// Wraps inline content, e.g. in `**`. Surrounding whitespace is moved outside of the markers.
+fn wrap_inline(marker: &str, content: &str) -> String {
+    let trimmed = content.trim();
+    if trimmed.len() == 0 {
+        return content.to_string();
+    }
+    let leading = if content.starts_with(char::is_whitespace) { " " } else { "" };
+    let trailing = if content.ends_with(char::is_whitespace) { " " } else { "" };
+    return format!("{}{}{}{}{}", leading, marker, trimmed, marker, trailing);
+}
+
+fn text_content(node: NodeRef<Node>) -> String {
+    let mut output = String::new();
+    for descendant in node.descendants() {
+        if let Some(text) = descendant.value().as_text() {
+            output += &text[..];
+        } else if let Some(element) = descendant.value().as_element() {
+            if element.name() == "br" {
+                output += "\n";
+            }
+        }
+    }
+    return output;
+}
+
+fn escape_markdown(text: &str) -> String {
+    let mut output = String::new();
+    for ch in text.chars() {
+        if "\\`*_[]<>".contains(ch) {
+            output.push('\\');
+        }
+        output.push(ch);
+    }
+    return output;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(yaml: &str, html: &str) -> String {
+        let trafo_config: HtmlToMarkdownConversion = serde_yaml::from_str(yaml).unwrap();
+        return HtmlToMarkdownConverter::from_config(&trafo_config).unwrap().convert(html);
+    }
+
+    #[test]
+    fn converts_inline_formatting() {
+        assert_eq!(convert("{}", "<h2>Title</h2><p>Some <b>bold</b> and <i>italic</i> <a href=\"https://x.org\">link</a>.</p>"),
+                   "## Title\n\nSome **bold** and *italic* [link](https://x.org).\n");
+        assert_eq!(convert("{}", "<blockquote><p>quoted</p></blockquote><p>a<br>b</p>"), "> quoted\n\na  \nb\n");
+    }
+
+    #[test]
+    fn converts_lists_tables_and_code() {
+        assert_eq!(convert("{}", "<ul><li>one</li><li>two <code>x</code></li></ul><ol><li>a</li></ol>"),
+                   "- one\n- two `x`\n\n1. a\n");
+        assert_eq!(convert("{}", "<table><thead><tr><th>A</th><th>B</th></tr></thead><tbody><tr><td>1|2</td><td>3</td></tr></tbody></table>"),
+                   "| A | B |\n| --- | --- |\n| 1\\|2 | 3 |\n");
+        assert_eq!(convert("{}", "<pre><code class=\"language-rust\">fn main() {}\n</code></pre>"),
+                   "```rust\nfn main() {}\n```\n");
+    }
+
+    #[test]
+    fn converts_or_skips_images() {
+        let html = "<p>Logo: <img alt=\"logo\" src=\"l.png\"></p>";
+        assert_eq!(convert("{}", html), "Logo: ![logo](l.png)\n");
+        assert_eq!(convert("{ skip_images: true }", html), "Logo:\n");
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::{HashMap, HashSet};
+use ego_tree::NodeRef;
+use regex::Regex;
+use scraper::{Html, Node};
+use crate::config::HtmlSanitization;
+use crate::transformer::Transformer;
+
+const VOID_TAGS: [&str; 3] = ["br", "hr", "img"];
+const BLOCK_TAGS: [&str; 24] = [
+    "address", "article", "aside", "blockquote", "caption", "dd", "div", "dl", "dt", "figure",
+    "footer", "h1", "h2", "h3", "h4", "h5", "h6", "header", "li", "ol", "p", "pre", "table", "ul",
+];
+
+/// The cleaned HTML and the matching plain text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SanitizedHtml {
+    pub html: String,
+    pub text: String,
+}
+
+/// Removes everything but an allowlist of tags and attributes from HTML, e.g. inline styles,
+/// `mso-` classes, conditional comments, scripts and tracking pixels.
+#[derive(Debug, Clone)]
+pub struct HtmlSanitizer {
+    allowed_tags: HashSet<String>,
+    allowed_attributes: HashMap<String, HashSet<String>>,
+    removed_tags: HashSet<String>,
+    whitespace: Regex,
+    line_whitespace: Regex,
+    blank_lines: Regex,
+}
+
+impl HtmlSanitizer {
+    pub fn from_config(sanitization: &HtmlSanitization) -> anyhow::Result<HtmlSanitizer> {
+        return Ok(HtmlSanitizer {
+            allowed_tags: sanitization.allowed_tags().iter().map(|tag| tag.to_lowercase()).collect(),
+            allowed_attributes: sanitization.allowed_attributes().iter()
+                .map(|(tag, attributes)| (tag.to_lowercase(), attributes.iter().map(|attribute| attribute.to_lowercase()).collect()))
+                .collect(),
+            removed_tags: sanitization.removed_tags().iter().map(|tag| tag.to_lowercase()).collect(),
+            whitespace: Regex::new(r"\s+")?,
+            line_whitespace: Regex::new(r" *\n *")?,
+            blank_lines: Regex::new(r"\n{3,}")?,
+        });
+    }
+
+    /// Sanitizes the HTML and applies the transformer to all text nodes.
+    pub fn sanitize(&self, html: &str, transformer: &dyn Transformer) -> anyhow::Result<SanitizedHtml> {
+        let document = Html::parse_document(html);
+        let mut output = SanitizedHtml {
+            html: String::new(),
+            text: String::new(),
+        };
+        self.sanitize_children(document.tree.root(), transformer, false, &mut output)?;
+
+        let text = self.line_whitespace.replace_all(output.text.as_str(), "\n");
+        let text = self.blank_lines.replace_all(text.as_ref(), "\n\n");
+        return Ok(SanitizedHtml {
+            html: output.html.trim().to_string(),
+            text: text.trim().to_string(),
+        });
+    }
+
+    fn sanitize_children(&self, node: NodeRef<Node>, transformer: &dyn Transformer, preformatted: bool, output: &mut SanitizedHtml) -> anyhow::Result<()> {
+        for child in node.children() {
+            self.sanitize_node(child, transformer, preformatted, output)?;
+        }
+        return Ok(());
+    }
+
+    fn sanitize_node(&self, node: NodeRef<Node>, transformer: &dyn Transformer, preformatted: bool, output: &mut SanitizedHtml) -> anyhow::Result<()> {
+        match node.value() {
+            Node::Text(text) => {
+                let text = transformer.execute(&text[..])?;
+                output.html += escape_html(text.as_str()).as_str();
+                if preformatted {
+                    output.text += text.as_str();
+                } else {
+                    let text = self.whitespace.replace_all(text.as_str(), " ");
+                    if output.text.len() == 0 || output.text.ends_with('\n') {
+                        output.text += text.trim_start();
+                    } else {
+                        output.text += text.as_ref();
+                    }
+                }
+            }
+            Node::Element(element) => {
+                let name = element.name().to_lowercase();
+                if self.removed_tags.contains(&name) || is_tracking_pixel(name.as_str(), element) {
+                    return Ok(());
+                }
+                let is_block = BLOCK_TAGS.contains(&name.as_str());
+                if is_block {
+                    ensure_line_break(&mut output.text);
+                }
+                match name.as_str() {
+                    "br" => output.text += "\n",
+                    "td" | "th" if !output.text.ends_with('\n') => output.text += "\t",
+                    "li" => output.text += "- ",
+                    _ => {}
+                }
+
+                let is_allowed = self.allowed_tags.contains(&name);
+                if is_allowed {
+                    output.html += format!("<{}", name).as_str();
+                    for (attribute, value) in element.attrs() {
+                        let attribute = attribute.to_lowercase();
+                        if self.is_allowed_attribute(name.as_str(), attribute.as_str(), value) {
+                            output.html += format!(" {}=\"{}\"", attribute, escape_html(value)).as_str();
+                        }
+                    }
+                    output.html += ">";
+                }
+                self.sanitize_children(node, transformer, preformatted || name == "pre", output)?;
+                if is_allowed && !VOID_TAGS.contains(&name.as_str()) {
+                    output.html += format!("</{}>", name).as_str();
+                }
+
+                if is_block || name == "tr" {
+                    ensure_line_break(&mut output.text);
+                }
+            }
+            Node::Document | Node::Fragment => {
+                self.sanitize_children(node, transformer, preformatted, output)?;
+            }
+            _ => {
+                // comments (including conditional comments), doctypes and processing instructions are dropped
+            }
+        }
+        return Ok(());
+    }
+
+    fn is_allowed_attribute(&self, tag: &str, attribute: &str, value: &str) -> bool {
+        let is_allowed = [tag, "*"].iter()
+            .filter_map(|tag| self.allowed_attributes.get(*tag))
+            .any(|attributes| attributes.contains(attribute));
+        if !is_allowed {
+            return false;
+        }
+        if attribute == "href" || attribute == "src" {
+            return is_allowed_url(attribute, value);
+        }
+        return true;
+    }
+}
+
+/// Allows relative URLs, `http`, `https` and `mailto` URLs, and `data:image/...` URLs for `src`.
+/// Browsers ignore whitespace and control characters in URLs (e.g. `jav&#x09;ascript:`), so the
+/// scheme is checked without them.
+fn is_allowed_url(attribute: &str, value: &str) -> bool {
+    let url: String = value.chars()
+        .filter(|ch| !ch.is_ascii_whitespace() && !ch.is_control())
+        .collect::<String>()
+        .to_lowercase();
+    let scheme_end = match url.find(|ch| ch == ':' || ch == '/' || ch == '?' || ch == '#') {
+        Some(index) if url[index..].starts_with(':') => index,
+        // no scheme, i.e. a relative URL
+        _ => return true,
+    };
+    match &url[..scheme_end] {
+        "http" | "https" | "mailto" => return true,
+        "data" => return attribute == "src" && url.starts_with("data:image/"),
+        _ => return false,
+    }
+}
+
+/// Images with a size of at most 1x1 pixel are used to track whether an email has been read.
+fn is_tracking_pixel(tag: &str, element: &scraper::node::Element) -> bool {
+    if tag != "img" {
+        return false;
+    }
+    let is_tiny = |attribute: &str| element.attr(attribute)
+        .map(|value| value.trim().trim_end_matches("px").parse::<u32>().map(|size| size <= 1).unwrap_or(false))
+        .unwrap_or(false);
+    return is_tiny("width") && is_tiny("height");
+}
+
+fn ensure_line_break(text: &mut String) {
+    if text.len() > 0 && !text.ends_with('\n') {
+        text.push('\n');
+    }
+}
+
+pub fn escape_html(text: &str) -> String {
+    return text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Uppercase;
+
+    impl Transformer for Uppercase {
+        fn execute(&self, text: &str) -> anyhow::Result<String> {
+            return Ok(text.to_uppercase());
+        }
+    }
+
+    fn sanitize(yaml: &str, html: &str) -> SanitizedHtml {
+        let sanitization: HtmlSanitization = serde_yaml::from_str(yaml).unwrap();
+        return HtmlSanitizer::from_config(&sanitization).unwrap().sanitize(html, &Uppercase).unwrap();
+    }
+
+    #[test]
+    fn keeps_allowed_tags_and_attributes_only() {
+        let sanitized = sanitize("{}", "<p class=\"MsoNormal\" style=\"x\">Mail <b>me</b><o:p></o:p></p>\
+            <p><a href=\"javascript:alert(1)\" onclick=\"x\">bad</a> <a href=\"https://x.org?a=1&amp;b=2\">good</a></p>\
+            <script>alert(1)</script><table><tr><td>1 &lt; 2</td></tr></table>");
+        assert_eq!(sanitized.html, "<p>MAIL <b>ME</b></p><p><a>BAD</a> <a href=\"https://x.org?a=1&amp;b=2\">GOOD</a></p>\
+            <table><tbody><tr><td>1 &lt; 2</td></tr></tbody></table>");
+        assert_eq!(sanitized.text, "MAIL ME\nBAD GOOD\n1 < 2");
+    }
+
+    #[test]
+    fn uses_configured_allowlists() {
+        let sanitized = sanitize("{ allowed_tags: [p, span], allowed_attributes: { span: [class] }, removed_tags: [b] }",
+                                 "<p><span class=\"x\" id=\"y\">a</span><b>gone</b><i>kept</i></p>");
+        assert_eq!(sanitized.html, "<p><span class=\"x\">A</span>KEPT</p>");
+    }
+
+    #[test]
+    fn drops_urls_with_disallowed_schemes() {
+        let sanitized = sanitize("{}", "<a href=\"jav&#x09;ascript:alert(1)\">a</a><a href=\" JAVASCRIPT:x\">b</a>\
+            <a href=\"data:text/html,<script>x</script>\">c</a><a href=\"ftp://x.org\">d</a>");
+        assert_eq!(sanitized.html, "<a>A</a><a>B</a><a>C</a><a>D</a>");
+        let sanitized = sanitize("{}", "<a href=\"mailto:a@b.org\">a</a><a href=\"docs/x.html#top\">b</a>\
+            <img src=\"data:image/png;base64,AAAA\"><a href=\"data:image/png;base64,AAAA\">c</a>");
+        assert_eq!(sanitized.html, "<a href=\"mailto:a@b.org\">A</a><a href=\"docs/x.html#top\">B</a>\
+            <img src=\"data:image/png;base64,AAAA\"><a>C</a>");
+    }
+
+    #[test]
+    fn escapes_html() {
+        assert_eq!(escape_html("<a href=\"x\">&</a>"), "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;");
+    }
+}
@@ -0,0 +1,199 @@
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+/// PNG chunks with metadata: EXIF, text (including XMP in `iTXt`) and the modification time.
+const PNG_METADATA_CHUNKS: [&[u8; 4]; 5] = [b"eXIf", b"tEXt", b"zTXt", b"iTXt", b"tIME"];
+
+const JPEG_SOI: u8 = 0xd8;
+const JPEG_SOS: u8 = 0xda;
+const JPEG_APP0: u8 = 0xe0;
+const JPEG_APP1: u8 = 0xe1;
+const JPEG_APP2: u8 = 0xe2;
+const JPEG_APP13: u8 = 0xed;
+const JPEG_APP14: u8 = 0xee;
+const JPEG_APP15: u8 = 0xef;
+const JPEG_COM: u8 = 0xfe;
+
+/// The image without metadata and the kinds of metadata that have been removed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrippedImage {
+    pub data: Vec<u8>,
+    pub removed: Vec<String>,
+}
+
+/// Removes EXIF (including GPS positions), XMP, IPTC, comments and text chunks from PNG and JPEG
+/// images. The image data and color information (ICC profiles, JFIF and Adobe segments) are kept
+/// byte for byte, so the image is not re-encoded.
+pub fn strip_metadata(data: &[u8]) -> anyhow::Result<StrippedImage> {
+    if data.starts_with(&PNG_SIGNATURE) {
+        return strip_png(data);
+    }
+    if data.starts_with(&[0xff, JPEG_SOI]) {
+        return strip_jpeg(data);
+    }
+    return Err(anyhow::Error::msg("Unsupported image format, only PNG and JPEG images can be stripped."));
+}
+
+fn strip_png(data: &[u8]) -> anyhow::Result<StrippedImage> {
+    let mut result = StrippedImage {
+        data: PNG_SIGNATURE.to_vec(),
+        removed: Vec::new(),
+    };
+    let mut position = PNG_SIGNATURE.len();
+    while position < data.len() {
+        let header = data.get(position..position + 8)
+            .ok_or_else(|| anyhow::Error::msg(format!("Truncated PNG chunk at byte {}.", position)))?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let chunk_type = &header[4..8];
+        // length, type, data and CRC
+        let end = position + 12 + length;
+        if end > data.len() {
+            return Err(anyhow::Error::msg(format!("Truncated PNG chunk at byte {}.", position)));
+        }
+        if PNG_METADATA_CHUNKS.iter().any(|metadata| &metadata[..] == chunk_type) {
+            add_removed(&mut result.removed, png_chunk_label(chunk_type, &data[position + 8..end - 4]));
+        } else {
+            result.data.extend_from_slice(&data[position..end]);
+        }
+        position = end;
+        if chunk_type == b"IEND" {
+            break;
+        }
+    }
+    return Ok(result);
+}
+
+fn png_chunk_label(chunk_type: &[u8], content: &[u8]) -> &'static str {
+    match chunk_type {
+        b"eXIf" => return "EXIF",
+        b"tIME" => return "timestamp",
+        _ => {
+            if content.starts_with(b"XML:com.adobe.xmp\0") {
+                return "XMP";
+            }
+            return "text";
+        }
+    }
+}
+
+fn strip_jpeg(data: &[u8]) -> anyhow::Result<StrippedImage> {
+    let mut result = StrippedImage {
+        data: data[..2].to_vec(),
+        removed: Vec::new(),
+    };
+    let mut position = 2;
+    while position < data.len() {
+        if data[position] != 0xff {
+            return Err(anyhow::Error::msg(format!("Invalid JPEG marker at byte {}.", position)));
+        }
+        let marker = match data.get(position + 1) {
+            Some(marker) => *marker,
+            None => break,
+        };
+        // fill bytes before a marker
+        if marker == 0xff {
+            position += 1;
+            continue;
+        }
+        // markers without a segment
+        if marker == 0x01 || (0xd0..=0xd7).contains(&marker) {
+            result.data.extend_from_slice(&data[position..position + 2]);
+            position += 2;
+            continue;
+        }
+        let length = data.get(position + 2..position + 4)
+            .map(|length| u16::from_be_bytes([length[0], length[1]]) as usize)
+            .ok_or_else(|| anyhow::Error::msg(format!("Truncated JPEG segment at byte {}.", position)))?;
+        let end = position + 2 + length;
+        if length < 2 || end > data.len() {
+            return Err(anyhow::Error::msg(format!("Truncated JPEG segment at byte {}.", position)));
+        }
+        if marker == JPEG_SOS {
+            // the compressed image data follows the start of scan until the end of the image
+            result.data.extend_from_slice(&data[position..]);
+            break;
+        }
+        let content = &data[position + 4..end];
+        match jpeg_segment_label(marker, content) {
+            Some(label) => add_removed(&mut result.removed, label),
+            None => result.data.extend_from_slice(&data[position..end]),
+        }
+        position = end;
+    }
+    return Ok(result);
+}
+
+/// Returns the kind of metadata of an application or comment segment, or None if it is kept.
+fn jpeg_segment_label(marker: u8, content: &[u8]) -> Option<&'static str> {
+    match marker {
+        JPEG_APP1 => {
+            if content.starts_with(b"Exif\0") {
+                return Some("EXIF");
+            }
+            return Some("XMP");
+        }
+        // ICC profiles are needed to display the colors correctly, other APP2 segments are dropped
+        JPEG_APP2 if content.starts_with(b"ICC_PROFILE\0") => return None,
+        JPEG_APP13 => return Some("IPTC"),
+        JPEG_COM => return Some("comment"),
+        JPEG_APP0 | JPEG_APP14 => return None,
+        JPEG_APP2..=JPEG_APP15 => return Some("application data"),
+        _ => return None,
+    }
+}
+
+fn add_removed(removed: &mut Vec<String>, label: &str) {
+    if !removed.iter().any(|existing| existing == label) {
+        removed.push(label.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png_chunk(chunk_type: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(chunk_type);
+        chunk.extend_from_slice(data);
+        // the CRC is not checked
+        chunk.extend_from_slice(&[1, 2, 3, 4]);
+        return chunk;
+    }
+
+    fn jpeg_segment(marker: u8, data: &[u8]) -> Vec<u8> {
+        let mut segment = vec![0xff, marker];
+        segment.extend_from_slice(&((data.len() + 2) as u16).to_be_bytes());
+        segment.extend_from_slice(data);
+        return segment;
+    }
+
+    #[test]
+    fn strips_png_metadata_chunks() {
+        let header = png_chunk(b"IHDR", &[0; 13]);
+        let image_data = png_chunk(b"IDAT", &[9; 5]);
+        let end = png_chunk(b"IEND", &[]);
+        let png = [&PNG_SIGNATURE[..], &header, &png_chunk(b"eXIf", b"MM\0*"),
+            &png_chunk(b"iTXt", b"XML:com.adobe.xmp\0\0\0\0\0<x/>"), &image_data, &end].concat();
+        let stripped = strip_metadata(png.as_slice()).unwrap();
+        assert_eq!(stripped.removed, vec!["EXIF", "XMP"]);
+        assert_eq!(stripped.data, [&PNG_SIGNATURE[..], &header, &image_data, &end].concat());
+        assert_eq!(strip_metadata(&png[..20]).unwrap_err().to_string(), "Truncated PNG chunk at byte 8.");
+    }
+
+    #[test]
+    fn strips_jpeg_metadata_segments() {
+        let jfif = jpeg_segment(JPEG_APP0, b"JFIF\0xx");
+        let icc = jpeg_segment(JPEG_APP2, b"ICC_PROFILE\0x");
+        let tables = jpeg_segment(0xdb, b"q");
+        let scan = [&jpeg_segment(JPEG_SOS, b"s")[..], &[1, 2, 0xff, 0, 3, 0xff, 0xd9]].concat();
+        let jpeg = [&[0xff, JPEG_SOI][..], &jfif, &jpeg_segment(JPEG_APP1, b"Exif\0\0GPS"), &icc,
+            &jpeg_segment(JPEG_COM, b"hi"), &tables, &scan].concat();
+        let stripped = strip_metadata(jpeg.as_slice()).unwrap();
+        assert_eq!(stripped.removed, vec!["EXIF", "comment"]);
+        assert_eq!(stripped.data, [&[0xff, JPEG_SOI][..], &jfif, &icc, &tables, &scan].concat());
+    }
+
+    #[test]
+    fn rejects_other_formats() {
+        assert!(strip_metadata(b"GIF89a").is_err());
+    }
+}
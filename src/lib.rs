@@ -0,0 +1,34 @@
+//! Text transformation engine of Clipboard Cleaner.
+//!
+//! The configuration types are in [`config`], profiles are turned into executable
+//! [`text_transformation::TextTransformation`]s, which implement the [`transformer::Transformer`]
+//! trait. Applications can add their own transformation types via
+//! [`transformer::TransformerRegistry`].
+
+pub use crate::config::{Config, Transformation, TransformationAction};
+
+pub mod config;
+pub mod config_loader;
+pub mod text_transformation;
+pub mod transformer;
+pub mod char_filter;
+pub mod emoji_transformer;
+pub mod script_transformer;
+pub mod command_transformer;
+pub mod secret_detector;
+pub mod pii_redactor;
+pub mod snippet_cleaner;
+pub mod html_markdown;
+pub mod html_sanitizer;
+pub mod rtf_decoder;
+pub mod table_converter;
+pub mod structured_data;
+pub mod charset;
+pub mod byte_codec;
+pub mod image_metadata;
+pub mod file_list;
+pub mod output_targets;
+pub mod charset_detection;
+pub mod mojibake;
+pub mod selection;
+pub mod cli;
@@ -1,34 +1,40 @@
 extern crate core;
 
+use std::path::PathBuf;
 use std::sync::Arc;
-use gdk::Atom;
 
 use gio::ApplicationFlags;
 use gio::prelude::*;
 use gtk::Application;
-use gtk::gdk::SELECTION_CLIPBOARD;
-use crate::config::{Config, Transformation, TransformationAction};
-use crate::config_loader::ConfigurationLoaderBuilder;
+use clipboard_cleaner::config::Config;
+use clipboard_cleaner::config_loader::ConfigurationLoaderBuilder;
 use crate::main_window::MainWindow;
+use clipboard_cleaner::cli::{parse_args, SelectionOptions, USAGE};
 
-mod config;
-mod config_loader;
 mod main_window;
 mod profile_dropdown;
 mod encoding_dropdown;
 mod targets_list;
 mod content_textbox;
-mod text_transformation;
-mod char_filter;
 
-const DEFAULT_SELECTION: Atom = SELECTION_CLIPBOARD;
-
-fn new_ui(app: Arc<Application>, config: Arc<Config>) -> anyhow::Result<Arc<MainWindow>> {
-    let main_window = MainWindow::new(app.clone(), config)?;
+fn new_ui(app: Arc<Application>, config: Arc<Config>, options: SelectionOptions) -> anyhow::Result<Arc<MainWindow>> {
+    let main_window = MainWindow::new(app.clone(), config, options)?;
     return Ok(main_window.clone());
 }
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let options = match parse_args(&args[1..]) {
+        Ok(options) if options.help => {
+            println!("{}", USAGE);
+            std::process::exit(0);
+        }
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\n{}", err, USAGE);
+            std::process::exit(2);
+        }
+    };
     glib::set_program_name(Some("clipboard-cleaner"));
     glib::set_application_name("clipboard-cleaner");
     let app = Arc::new(gtk::Application::new(Some("net.laerrus.ClipboardCleaner"),
@@ -37,7 +43,11 @@ fn main() {
     let mut config_loader = ConfigurationLoaderBuilder::new( "net.laerrus", "Laerrus Ultd.", "clipboard-cleaner");
     config_loader.base_name("clipboard-cleaner");
     let config_loader = config_loader.build();
-    let mut config: anyhow::Result<Config> = config_loader.load_configuration();
+    let mut config: anyhow::Result<Config> = config_loader.load_configuration_with_path()
+        .map(|(mut config, path): (Config, PathBuf)| {
+            config.set_base_dir(path.parent().map(|dir| dir.to_path_buf()));
+            return config;
+        });
     if config.is_err() {
         println!("No configuration file found. Using integrated default config.");
         let config_yaml = include_str!("assets/default-config.yaml");
@@ -49,7 +59,7 @@ fn main() {
     let app_clone = app.clone();
     app.connect_activate(move |_app_ref| {
         let app_clone = app_clone.clone();
-        new_ui(app_clone.clone(), config.clone())
+        new_ui(app_clone.clone(), config.clone(), options)
             .expect("could not build UI")
             .on_delete(move |_window| {
                 let app_clone = app_clone.clone();
@@ -66,5 +76,6 @@ fn main() {
     // }).collect::<String>();
     // println!("transformed1: {}",  transformed1.as_str());
 
-    app.run();
+    // the options have been parsed already, GTK would reject them
+    app.run_with_args(&args[..1]);
 }
@@ -3,35 +3,70 @@ use std::io::Write;
 use std::ops::Deref;
 use std::str::FromStr;
 use std::sync::Arc;
-use encoding::all::{ASCII, ISO_8859_1, ISO_8859_15, UTF_16BE, UTF_16LE, UTF_8};
+use encoding::all::{ISO_8859_1, UTF_8};
 use encoding::{DecoderTrap, Encoding};
-use gdk::prelude::WindowExtManual;
 use glib::ObjectExt;
 use glib::signal::Inhibit;
-use gtk::{Application, ApplicationWindow, Clipboard, ComboBox, TextView};
-use gtk::prelude::{ApplicationWindowExt, BuilderExt, BuilderExtManual, ButtonExt, GtkWindowExt, LabelExt, TextBufferExt, TextViewExt, TreeModelExt, TreeSelectionExt, WidgetExt};
+use gtk::{Application, ApplicationWindow, ComboBox, ComboBoxText, IconSize, TargetEntry, TargetFlags, TextView};
+use gtk::gdk_pixbuf::{Pixbuf, PixbufLoader};
+use gtk::prelude::{BuilderExt, BuilderExtManual, ButtonExt, ComboBoxExt, ComboBoxTextExt, GtkWindowExt, ImageExt, LabelExt, PixbufLoaderExt, StackExt, TextBufferExt, TextViewExt, TreeModelExt, TreeSelectionExt, WidgetExt};
+use itertools::Itertools;
 use parking_lot::{RwLock};
 use crate::content_textbox::ContentTextbox;
-use crate::{ Config, DEFAULT_SELECTION };
+use clipboard_cleaner::cli::SelectionOptions;
+use crate::Config;
 use crate::encoding_dropdown::EncodingDropdown;
 use crate::profile_dropdown::ProfileDropdown;
+use clipboard_cleaner::selection::Selection;
 use crate::targets_list::TargetsList;
-use crate::text_transformation::TextTransformation;
+use clipboard_cleaner::byte_codec::{hex_dump, hex_dump_ranges};
+use clipboard_cleaner::charset::{self, DecodeTrap, InvalidBytes, TrappedText};
+use clipboard_cleaner::charset_detection::{detect_charset, DetectedCharset};
+use clipboard_cleaner::config::OutputTarget;
+use clipboard_cleaner::file_list::file_paths;
+use clipboard_cleaner::html_sanitizer::HtmlSanitizer;
+use clipboard_cleaner::image_metadata::strip_metadata;
+use clipboard_cleaner::mojibake::MojibakeRepairer;
+use clipboard_cleaner::output_targets::{is_meta_target, is_replaced_by_text, OutputTargetEncoder, DEFAULT_TEXT_TARGETS, SANITIZED_HTML_TARGETS};
+use clipboard_cleaner::rtf_decoder::decode_rtf;
+use clipboard_cleaner::secret_detector::SecretDetector;
+use clipboard_cleaner::text_transformation::TextTransformation;
+use clipboard_cleaner::transformer::Transformer;
+
+const SECRET_TAG: &str = "secret";
+const SECRET_BACKGROUND: &str = "#ffb0b0";
+const BYTES_TAG: &str = "bytes";
+const BYTES_BACKGROUND: &str = "#b0d0ff";
+const INVALID_TAG: &str = "invalid";
+const INVALID_BACKGROUND: &str = "#ffd080";
+/// Image targets which cannot contain metadata and are republished unchanged by "Strip Metadata".
+const METADATA_FREE_IMAGE_TARGETS: [&str; 3] = ["image/bmp", "image/x-bmp", "image/x-ms-bmp"];
 
 pub struct MainWindow {
     app: Arc<Application>,
     window: Arc<ApplicationWindow>,
     config: Arc<Config>,
     gui_replacement_profile: Option<TextTransformation>,
+    secret_detector: SecretDetector,
+    mojibake_repairer: MojibakeRepairer,
 
     on_delete_handler: RwLock<Cell<Box<dyn Fn(&MainWindow) -> () + 'static>>>,
 
+    selection: RwLock<Selection>,
+    /// The selection cleaned text is written to, `None` for the selection it has been read from.
+    output_selection: RwLock<Option<Selection>>,
     current_target: RwLock<String>,
     current_data: RwLock<Vec<u8>>,
+    /// Byte offset of each character of the text view within `current_data`, if known.
+    char_offsets: RwLock<Option<Vec<usize>>>,
+    /// The detected charset of the current target, `None` if the target declares its charset.
+    detected_charset: RwLock<Option<DetectedCharset>>,
+    /// The byte sequences of the current data which are invalid in the selected encoding.
+    invalid_bytes: RwLock<Vec<InvalidBytes>>,
 }
 
 impl MainWindow {
-    pub fn new(app: Arc<Application>, config: Arc<Config>) -> anyhow::Result<Arc<MainWindow>> {
+    pub fn new(app: Arc<Application>, config: Arc<Config>, options: SelectionOptions) -> anyhow::Result<Arc<MainWindow>> {
         let glade_src = include_str!("assets/main_window.glade");
         let builder = gtk::Builder::from_string(glade_src);
         builder.set_application(&*app);
@@ -51,7 +86,7 @@ impl MainWindow {
         let treeview: gtk::TreeView = builder.object("targets_treeview")
             .expect("could not create targets list.");
         let treeview = Arc::new(treeview);
-        let targets_list = TargetsList::new(app.clone(), treeview.clone());
+        let targets_list = TargetsList::new(app.clone(), treeview.clone(), options.selection);
 
         let info_label: gtk::Label = builder.object("info_label")
             .expect("could not create info label.");
@@ -62,11 +97,27 @@ impl MainWindow {
         let textbox = Arc::new(textbox);
         let content_textbox = ContentTextbox::new(app.clone(), textbox.clone());
 
-        let encoding_dropdown: ComboBox = builder.object("encoding_dropdown")
+        let hex_textview: TextView = builder.object("hex_textview")
+            .expect("could not create hex view.");
+        let hex_textbox = ContentTextbox::new(app.clone(), Arc::new(hex_textview));
+
+        let content_stack: gtk::Stack = builder.object("content_stack")
+            .expect("could not create content stack.");
+        let image_preview: gtk::Image = builder.object("image_preview")
+            .expect("could not create image preview.");
+
+        let encoding_dropdown: ComboBoxText = builder.object("encoding_dropdown")
             .expect("could not create encoding dropdown.");
         let encoding_dropdown = Arc::new(encoding_dropdown);
         let encoding_dropdown = EncodingDropdown::new(app.clone(), encoding_dropdown.clone());
 
+        let encoding_confidence_label: gtk::Label = builder.object("encoding_confidence_label")
+            .expect("could not create encoding confidence label.");
+        let decode_trap_dropdown: ComboBox = builder.object("decode_trap_dropdown")
+            .expect("could not create decode trap dropdown.");
+        let invalid_bytes_dropdown: ComboBoxText = builder.object("invalid_bytes_dropdown")
+            .expect("could not create invalid bytes dropdown.");
+
         let profiles_dropdown: ComboBox = builder.object("cleanup_profile_dropdown")
             .expect("could not create profiles dropdown.");
         let profiles_dropdown = Arc::new(profiles_dropdown);
@@ -78,14 +129,23 @@ impl MainWindow {
             None
         };
 
+        let secret_detector = SecretDetector::from_config(&*config)?;
+
         let result = Arc::new(MainWindow {
             app: app.clone(),
             window: Arc::new(window),
             config: config.clone(),
             gui_replacement_profile,
+            secret_detector,
+            mojibake_repairer: MojibakeRepairer::new(),
             on_delete_handler: RwLock::new(Cell::new(Box::new(|_| {()}))),
+            selection: RwLock::new(options.selection),
+            output_selection: RwLock::new(options.output_selection),
             current_target: RwLock::new(String::new()),
             current_data: RwLock::new(Vec::new()),
+            char_offsets: RwLock::new(None),
+            detected_charset: RwLock::new(None),
+            invalid_bytes: RwLock::new(Vec::new()),
         });
 
         let result_clone = result.clone();
@@ -97,7 +157,7 @@ impl MainWindow {
         });
 
         let result_clone = result.clone();
-        result.window.connect_key_press_event(move |widget, key| {
+        result.window.connect_key_press_event(move |_widget, key| {
             let result_clone = result_clone.clone();
             if key.keyval() == gdk::keys::constants::Escape {
                 result_clone.window.close();
@@ -107,29 +167,58 @@ impl MainWindow {
 
         let result_clone = result.clone();
         let encoding_dropdown_clone = encoding_dropdown.clone();
-        // let info_label_clone = info_label.clone();
-        let content_textbox_clone = content_textbox.clone();
+        let info_label_clone = info_label.clone();
+        let hex_textbox_clone = hex_textbox.clone();
         targets_list.on_selection(move |_targets_list, selection| {
             let result_clone = result_clone.clone();
             let encoding_dropdown_clone = encoding_dropdown_clone.clone();
-            // let info_label_clone = info_label_clone.clone();
-            // let content_textbox_clone = content_textbox_clone.clone();
+            let info_label_clone = info_label_clone.clone();
             let selection = selection.selected();
             if let Some((model, iterator)) = selection {
                 let target_name = model.value(&iterator, 0).get::<String>();
                 if let Ok(target_name) = target_name {
                     let target = gdk::Atom::intern(target_name.as_str());
-                    let clipboard = gtk::Clipboard::get(&DEFAULT_SELECTION);
+                    let clipboard = result_clone.selection().clipboard();
                     let content = clipboard.wait_for_contents(&target);
                     if let Some(content) = content {
                         let data = content.data();
-                        result_clone.set_data(data);
+                        hex_textbox_clone.set_content(hex_dump(data.as_slice()).as_str());
+                        let image = if is_image_target(target.name().as_str()) {
+                            Some(load_image(data.as_slice()))
+                        } else {
+                            None
+                        };
                         let target_encoding = get_target_encoding(target.name().as_str());
                         println!("target_encoding: {:?}", &target_encoding);
-                        encoding_dropdown_clone.set_encoding(target_encoding);
+                        let detected_charset = if target_encoding.is_none() && image.is_none() {
+                            detect_charset(data.as_slice())
+                        } else {
+                            None
+                        };
+                        println!("detected_charset: {:?}", &detected_charset);
+                        result_clone.set_data(data);
+                        result_clone.set_detected_charset(detected_charset.clone());
+                        let encoding = target_encoding.or(detected_charset.map(|detected| detected.encoding));
+                        encoding_dropdown_clone.set_encoding(encoding.as_deref());
+                        match image {
+                            Some(Ok(pixbuf)) => {
+                                info_label_clone.set_text(format!("Image, {}x{} pixels.", pixbuf.width(), pixbuf.height()).as_str());
+                                image_preview.set_from_pixbuf(Some(&pixbuf));
+                                content_stack.set_visible_child_name("image");
+                            }
+                            Some(Err(err)) => {
+                                info_label_clone.set_text(format!("Could not load image: {}", err).as_str());
+                                image_preview.set_from_icon_name(Some("image-missing"), IconSize::Dialog);
+                                content_stack.set_visible_child_name("image");
+                            }
+                            None => content_stack.set_visible_child_name("text"),
+                        }
                     }
                 }
             } else {
+                content_stack.set_visible_child_name("text");
+                hex_textbox_clone.set_content("");
+                result_clone.set_detected_charset(None);
                 encoding_dropdown_clone.set_encoding(None);
             }
         });
@@ -137,16 +226,39 @@ impl MainWindow {
         let result_clone = result.clone();
         let textbox_clone = textbox.clone();
         let info_label_clone = info_label.clone();
+        let content_textbox_clone = content_textbox.clone();
+        let hex_textbox_clone = hex_textbox.clone();
+        let decode_trap_dropdown_clone = decode_trap_dropdown.clone();
+        let invalid_bytes_dropdown_clone = invalid_bytes_dropdown.clone();
         encoding_dropdown.on_change(move |_dropdown, encoding| {
             let result_clone = result_clone.clone();
             let textbox_clone = textbox_clone.clone();
             let info_label_clone = info_label_clone.clone();
-            let text = convert_to_encoding(encoding.clone(), &result_clone.data());
-            if let Some(text) = text {
+            encoding_confidence_label.set_text(confidence_text(result_clone.detected_charset().as_ref(), encoding.as_deref()).as_str());
+            let trap = decode_trap(decode_trap_dropdown_clone.active_id().as_deref());
+            let decoded = convert_to_encoding(encoding.clone(), &result_clone.data(), trap);
+            let invalid_bytes = decoded.as_ref().map(|decoded| decoded.invalid_bytes.clone()).unwrap_or_default();
+            set_invalid_bytes_list(&invalid_bytes_dropdown_clone, &invalid_bytes, result_clone.data().as_slice());
+            hex_textbox_clone.clear_highlight(INVALID_TAG);
+            let hex_ranges = invalid_bytes.iter()
+                .flat_map(|invalid| hex_dump_ranges(invalid.start, invalid.end))
+                .collect::<Vec<(usize, usize)>>();
+            hex_textbox_clone.highlight(INVALID_TAG, INVALID_BACKGROUND, &hex_ranges);
+            result_clone.set_invalid_bytes(invalid_bytes.clone());
+            content_textbox_clone.clear_highlight(INVALID_TAG);
+            if let Some(decoded) = decoded {
+                let text = decoded.text;
                 println!("original text: {}", text.as_str());
                 let filtered_text = if let Some(gui_replacement_profile) = result_clone.gui_replacement_profile.clone() {
                     println!("using gui replacement profile: {:?}", &gui_replacement_profile);
-                    gui_replacement_profile.execute(text.as_str())
+                    match gui_replacement_profile.execute(text.as_str()) {
+                        Ok(filtered_text) => filtered_text,
+                        Err(err) => {
+                            info_label_clone.set_text(format!("GUI replacement profile failed: {}", err).as_str());
+                            textbox_clone.buffer().unwrap().set_text("");
+                            return;
+                        }
+                    }
                 } else {
                     println!("using default gui replacement function.");
                     text.to_string().chars()
@@ -157,40 +269,262 @@ impl MainWindow {
                         }).collect::<String>()
                 };
                 println!("filtered text: {}", filtered_text.as_str());
-                if text.as_str() != filtered_text.as_str() {
-                    info_label_clone.set_text("Clipboard text has been filtered to be viewable.");
+                // the byte offsets are only valid if filtering kept the characters in place
+                let char_offsets = if text.chars().count() == filtered_text.chars().count() {
+                    decoded.char_offsets
                 } else {
-                    info_label_clone.set_text("");
+                    None
+                };
+                result_clone.set_char_offsets(char_offsets);
+                let mut messages: Vec<String> = Vec::new();
+                if let Some(first) = invalid_bytes.first() {
+                    let encoding = encoding.clone().unwrap_or_default();
+                    if trap == DecodeTrap::Strict {
+                        messages.push(format!("Invalid {} at byte {}, decoding stopped there ({} invalid byte sequences).",
+                                              encoding, first.start, invalid_bytes.len()));
+                    } else {
+                        messages.push(format!("{} invalid {} byte sequences, the first at byte {}.",
+                                              invalid_bytes.len(), encoding, first.start));
+                    }
+                }
+                if text.as_str() != filtered_text.as_str() {
+                    messages.push("Clipboard text has been filtered to be viewable.".to_string());
                 }
                 textbox_clone.buffer().unwrap().set_text(filtered_text.as_str());
+                if result_clone.has_char_offsets() && trap != DecodeTrap::Strict {
+                    let ranges = invalid_bytes.iter()
+                        .map(|invalid| (invalid.char_index, invalid.char_index + replacement_length(trap, invalid)))
+                        .collect::<Vec<(usize, usize)>>();
+                    content_textbox_clone.highlight(INVALID_TAG, INVALID_BACKGROUND, &ranges);
+                }
+
+                content_textbox_clone.clear_highlight(SECRET_TAG);
+                let secrets = result_clone.secret_detector.detect(filtered_text.as_str());
+                if secrets.len() > 0 {
+                    let ranges = secrets.iter()
+                        .map(|secret| (filtered_text[..secret.start].chars().count(),
+                                       filtered_text[..secret.end].chars().count()))
+                        .collect::<Vec<(usize, usize)>>();
+                    content_textbox_clone.highlight(SECRET_TAG, SECRET_BACKGROUND, &ranges);
+                    let rules = secrets.iter().map(|secret| secret.rule.as_str()).unique().join(", ");
+                    messages.push(format!("Warning: possible secrets found ({}). Use a masking profile before pasting.", rules));
+                }
+                if let Some(repaired) = result_clone.mojibake_repairer.repair(text.as_str()) {
+                    let example = repaired.repairs.first()
+                        .map(|(broken, fixed)| format!(" (\"{}\" → \"{}\")", broken, fixed))
+                        .unwrap_or_default();
+                    messages.push(format!("Text looks like mojibake{}, a repair_mojibake profile can fix it.", example));
+                }
+                info_label_clone.set_text(messages.join(" ").as_str());
             } else {
+                result_clone.set_char_offsets(None);
                 info_label_clone.set_text(format!("Could not convert data to {:?}", &encoding).as_str());
                 textbox_clone.buffer().unwrap().set_text("");
             }
         });
 
+        let encoding_dropdown_clone = encoding_dropdown.clone();
+        decode_trap_dropdown.connect_changed(move |_dropdown| {
+            encoding_dropdown_clone.fire_change();
+        });
+
+        let result_clone = result.clone();
+        let content_textbox_clone = content_textbox.clone();
+        let hex_textbox_clone = hex_textbox.clone();
+        invalid_bytes_dropdown.connect_changed(move |dropdown| {
+            // the first entry only counts the invalid byte sequences
+            let index = dropdown.active_id().and_then(|id| id.as_str().parse::<usize>().ok());
+            let invalid = index.and_then(|index| result_clone.invalid_bytes().get(index).cloned());
+            if let Some(invalid) = invalid {
+                if let Some((first, _)) = hex_dump_ranges(invalid.start, invalid.end).first() {
+                    hex_textbox_clone.scroll_to_offset(*first);
+                }
+                if result_clone.has_char_offsets() {
+                    content_textbox_clone.scroll_to_offset(invalid.char_index);
+                }
+            }
+        });
+
+        let result_clone = result.clone();
+        let hex_textbox_clone = hex_textbox.clone();
+        content_textbox.on_selection(move |_textbox, start, end| {
+            hex_textbox_clone.clear_highlight(BYTES_TAG);
+            if let Some((start, end)) = result_clone.byte_range(start, end) {
+                let ranges = hex_dump_ranges(start, end);
+                hex_textbox_clone.highlight(BYTES_TAG, BYTES_BACKGROUND, &ranges);
+                if let Some((first, _)) = ranges.first() {
+                    hex_textbox_clone.scroll_to_offset(*first);
+                }
+            }
+        });
+
         let wipe_clipboard_button: gtk::Button = builder.object("wipe_clipboard_button")
             .expect("could not create wipe-clipboard button");
-        wipe_clipboard_button.connect_clicked(|_button| {
-            println!("wiping clipboard ...");
-            let clipboard = Clipboard::get(&DEFAULT_SELECTION);
+        let result_clone = result.clone();
+        wipe_clipboard_button.connect_clicked(move |_button| {
+            println!("wiping {} ...", result_clone.selection().name());
+            let clipboard = result_clone.selection().clipboard();
             clipboard.set_text("");
         });
 
+        let selection_dropdown: ComboBox = builder.object("selection_dropdown")
+            .expect("could not create selection dropdown.");
+        selection_dropdown.set_active_id(Some(options.selection.name()));
+        let result_clone = result.clone();
+        let targets_list_clone = targets_list.clone();
+        selection_dropdown.connect_changed(move |dropdown| {
+            let selection = dropdown.active_id().and_then(|id| Selection::from_str(id.as_str()).ok());
+            if let Some(selection) = selection {
+                println!("switching to {}", selection.name());
+                *result_clone.selection.write() = selection;
+                targets_list_clone.set_selection(selection);
+            }
+        });
+
+        let output_selection_dropdown: ComboBox = builder.object("output_selection_dropdown")
+            .expect("could not create output selection dropdown.");
+        output_selection_dropdown.set_active_id(Some(options.output_selection.map(|selection| selection.name()).unwrap_or("same")));
+        let result_clone = result.clone();
+        output_selection_dropdown.connect_changed(move |dropdown| {
+            // "same" writes to the selection the text has been read from
+            let output_selection = dropdown.active_id().and_then(|id| Selection::from_str(id.as_str()).ok());
+            *result_clone.output_selection.write() = output_selection;
+        });
+
+        let strip_metadata_button: gtk::Button = builder.object("strip_metadata_button")
+            .expect("could not create strip-metadata button");
+        let info_label_clone = info_label.clone();
+        let result_clone = result.clone();
+        strip_metadata_button.connect_clicked(move |_button| {
+            println!("stripping image metadata ...");
+            let clipboard = result_clone.selection().clipboard();
+            let mut images: Vec<(String, Vec<u8>)> = Vec::new();
+            let mut removed: Vec<String> = Vec::new();
+            let mut dropped: Vec<String> = Vec::new();
+            let mut stripped_count = 0;
+            for target in clipboard.wait_for_targets().unwrap_or(Vec::new()) {
+                let target_name = target.name().to_string();
+                if !is_image_target(target_name.as_str()) {
+                    continue;
+                }
+                let data = match clipboard.wait_for_contents(&target) {
+                    Some(content) => content.data(),
+                    None => continue,
+                };
+                match strip_metadata(data.as_slice()) {
+                    Ok(stripped) => {
+                        for label in stripped.removed {
+                            if !removed.contains(&label) {
+                                removed.push(label);
+                            }
+                        }
+                        images.push((target_name, stripped.data));
+                        stripped_count += 1;
+                    }
+                    Err(err) => {
+                        if METADATA_FREE_IMAGE_TARGETS.contains(&target_name.to_lowercase().as_str()) {
+                            images.push((target_name, data));
+                        } else {
+                            // targets which might contain metadata are not published again
+                            println!("dropping target {}: {}", &target_name, err);
+                            dropped.push(target_name);
+                        }
+                    }
+                }
+            }
+            if stripped_count == 0 {
+                info_label_clone.set_text("Clipboard has no PNG or JPEG image.");
+                return;
+            }
+            // text, HTML and file list targets are published again unchanged
+            let mut untouched = read_untouched_targets(result_clone.selection(), &images,
+                                                       |target| is_image_target(target) || is_meta_target(target));
+            images.append(&mut untouched);
+            if !set_clipboard_targets(result_clone.output_selection(), images) {
+                info_label_clone.set_text("Could not set the cleaned image on the clipboard.");
+                return;
+            }
+            let mut message = if removed.len() > 0 {
+                format!("Removed {} metadata.", removed.join(", "))
+            } else {
+                "The image has no metadata.".to_string()
+            };
+            if dropped.len() > 0 {
+                message += format!(" Dropped unsupported targets: {}.", dropped.join(", ")).as_str();
+            }
+            info_label_clone.set_text(message.as_str());
+        });
+
         let cleanup_text_button: gtk::Button = builder.object("cleanup_text_button")
             .expect("could not create cleanup-text button");
-        let profiles_dropdown_clone = profiles_dropdown.clone();
-        cleanup_text_button.connect_clicked(move |_button| {
-            // let profiles_dropdown_clone = profiles_dropdown_clone.clone();
+        let info_label_clone = info_label.clone();
+        let result_clone = result.clone();
+        cleanup_text_button.connect_clicked(move |button| {
+            let selection = result_clone.selection();
+            let output_selection = result_clone.output_selection();
             let profile = profiles_dropdown.profile();
             if let Some(profile) = profile {
                 println!("using transformation profile: {:?}", &profile);
                 let trafo = profile.transformation;
-                let content = content_textbox.content();
+                let profile = profile.profile;
+                if let Some(sanitization) = profile.html_sanitizer().clone() {
+                    let source_target = profile.source_target().clone().unwrap_or("text/html".to_string());
+                    let html = match read_target(selection, source_target.as_str()) {
+                        Some(html) => html,
+                        None => {
+                            info_label_clone.set_text(format!("Clipboard has no readable \"{}\" target.", source_target).as_str());
+                            return;
+                        }
+                    };
+                    let output_targets = profile.output_targets().clone()
+                        .unwrap_or(SANITIZED_HTML_TARGETS.iter().map(|target| OutputTarget::new(target)).collect());
+                    let info_label_clone = info_label_clone.clone();
+                    let button_clone = button.clone();
+                    button.set_sensitive(false);
+                    run_in_background(move || {
+                        return HtmlSanitizer::from_config(&sanitization)
+                            .and_then(|sanitizer| sanitizer.sanitize(html.as_str(), &trafo));
+                    }, move |sanitized| {
+                        button_clone.set_sensitive(true);
+                        let published = sanitized.and_then(|sanitized| {
+                            println!("sanitized html: {} bytes", sanitized.html.len());
+                            return publish_text(sanitized.text.as_str(), Some(sanitized.html.as_str()), Some(&output_targets),
+                                                profile.preserve_targets(), selection, output_selection);
+                        });
+                        if let Err(err) = published {
+                            println!("sanitizing failed: {}", err);
+                            info_label_clone.set_text(format!("Cleanup failed: {}", err).as_str());
+                        }
+                    });
+                    return;
+                }
+                let content = if let Some(source_target) = profile.source_target() {
+                    match read_target(selection, source_target.as_str()) {
+                        Some(content) => Some(content),
+                        None => {
+                            info_label_clone.set_text(format!("Clipboard has no readable \"{}\" target.", source_target).as_str());
+                            return;
+                        }
+                    }
+                } else {
+                    content_textbox.content()
+                };
                 if let Some(content) = content {
-                    let text = trafo.execute(content.as_str());
-                    println!("transformed text: {}", text);
-                    Clipboard::get(&DEFAULT_SELECTION).set_text(text.as_str());
+                    let info_label_clone = info_label_clone.clone();
+                    let button_clone = button.clone();
+                    button.set_sensitive(false);
+                    run_in_background(move || trafo.execute(content.as_str()), move |transformed| {
+                        button_clone.set_sensitive(true);
+                        let published = transformed.and_then(|text| {
+                            println!("transformed text: {} bytes", text.len());
+                            return publish_text(text.as_str(), None, profile.output_targets().as_ref(),
+                                                profile.preserve_targets(), selection, output_selection);
+                        });
+                        if let Err(err) = published {
+                            println!("transformation failed: {}", err);
+                            info_label_clone.set_text(format!("Cleanup failed: {}", err).as_str());
+                        }
+                    });
                 } else {
                     println!("no content to transform!");
                 }
@@ -212,10 +546,48 @@ impl MainWindow {
         self.on_delete_handler.write().set(Box::new(handler));
     }
 
+    pub fn selection(&self) -> Selection {
+        return *self.selection.read();
+    }
+
+    /// The selection cleaned text is written to.
+    pub fn output_selection(&self) -> Selection {
+        return self.output_selection.read().unwrap_or(self.selection());
+    }
+
     pub fn data(&self) -> Vec<u8> {
         return self.current_data.read().deref().clone();
     }
 
+    pub fn has_char_offsets(&self) -> bool {
+        return self.char_offsets.read().is_some();
+    }
+
+    pub fn invalid_bytes(&self) -> Vec<InvalidBytes> {
+        return self.invalid_bytes.read().clone();
+    }
+
+    pub fn set_invalid_bytes(&self, invalid_bytes: Vec<InvalidBytes>) {
+        *self.invalid_bytes.write() = invalid_bytes;
+    }
+
+    pub fn set_char_offsets(&self, char_offsets: Option<Vec<usize>>) {
+        *self.char_offsets.write() = char_offsets;
+    }
+
+    /// Returns the bytes of the current data the given characters of the text view were decoded from.
+    pub fn byte_range(&self, start: usize, end: usize) -> Option<(usize, usize)> {
+        return charset::byte_range(self.char_offsets.read().as_ref()?, start, end);
+    }
+
+    pub fn detected_charset(&self) -> Option<DetectedCharset> {
+        return self.detected_charset.read().clone();
+    }
+
+    pub fn set_detected_charset(&self, detected_charset: Option<DetectedCharset>) {
+        *self.detected_charset.write() = detected_charset;
+    }
+
     pub fn set_data(&self, data: Vec<u8>) {
         let mut current_data = self.current_data.write();
         current_data.clear();
@@ -224,16 +596,28 @@ impl MainWindow {
     }
 }
 
-fn get_target_encoding(target: &str) -> Option<&'static str> {
+fn get_target_encoding(target: &str) -> Option<String> {
     let target = target.to_lowercase();
     println!("get_target_encoding: target={}", target.as_str());
     match target.as_str() {
         "utf8_string" => {
-            return Some("utf-8");
+            return Some("utf-8".to_string());
         },
-        "string" | "text" => {
+        "string" => {
             // the "STRING" target is Latin-1 (aka ISO-8859-1), as defined by ICCCM
-            return Some("iso-8859-1");
+            return Some("iso-8859-1".to_string());
+        },
+        "text" => {
+            // the "TEXT" target's encoding is chosen by the owner application, so it is detected
+            return None;
+        },
+        "text/rtf" | "application/rtf" => {
+            // RTF is decoded to plain text, the document declares its own code page
+            return Some("rtf".to_string());
+        },
+        "text/uri-list" | "x-special/gnome-copied-files" => {
+            // the file:// URIs of copied files are shown as paths
+            return Some("uri-list".to_string());
         },
         _ => {
             let content_type = mime::Mime::from_str(target.as_str());
@@ -243,95 +627,69 @@ fn get_target_encoding(target: &str) -> Option<&'static str> {
                 println!("charset: {:?}", &charset);
                 if let Some(charset) = charset {
                     println!("unwrapped charset: {:?}", &charset);
-                    match charset.as_str() {
-                        "utf-8" => {
-                            return Some("utf-8");
-                        },
-                        "utf-16le" => {
-                            return Some("utf-16le");
-                        },
-                        "utf-16be" => {
-                            return Some("utf-16be");
-                        },
-                        "utf-16" | "unicode" => {
-                            return Some("utf-16");
-                        },
-                        "iso-8859-1" => {
-                            return Some("iso-8859-1");
-                        },
-                        "iso-8859-15" => {
-                            return Some("iso-8859-15");
-                        },
-                        "us-ascii" => {
-                            return Some("us-ascii");
-                        },
-                        _ => {}
-                    }
-                } else {
-                    if content_type.type_().as_str() == "text" {
-                        return Some("utf-8");
+                    // the charset may be any alias known to the encoding crate, e.g. "cp1252"
+                    if let Some(encoding) = charset::canonical_name(charset.as_str()) {
+                        return Some(encoding);
                     }
                 }
-            } else {
-                return Some("utf-8");
             }
-
+            // no declared charset, the charset is detected
             return None;
         }
     }
 }
 
-fn convert_to_encoding(encoding: Option<String>, data: &Vec<u8>) -> Option<String> {
+fn convert_to_encoding(encoding: Option<String>, data: &Vec<u8>, trap: DecodeTrap) -> Option<TrappedText> {
     if let Some(encoding) = encoding {
-        match encoding.to_lowercase().as_str() {
-            "utf-8" => {
-                return String::from_utf8(data.clone()).ok();
-            },
-            "utf-16le" => {
-                return UTF_16LE.decode(data.as_slice(), DecoderTrap::Replace).ok();
-            },
-            "utf-16be" => {
-                return UTF_16BE.decode(data.as_slice(), DecoderTrap::Replace).ok();
-            },
-            "utf-16" | "unicode" => {
-                let mut mode = 0;
-                if data.len() >= 2 {
-                    if *data.get(0).unwrap() == 0xFEu8 && *data.get(1).unwrap() == 0xFFu8 {
-                        mode = 1;
-                    } else if *data.get(0).unwrap() == 0xFFu8 && *data.get(1).unwrap() == 0xFEu8 {
-                        mode = 2
-                    } else {
-                        #[cfg(target_endian = "big")] { mode = 1; }
-                        #[cfg(target_endian = "little")] { mode = 2; }
-                    }
-                }
-                if mode == 1 {
-                    return UTF_16BE.decode(data.as_slice(), DecoderTrap::Replace).ok();
-                } else if mode == 2 {
-                    return UTF_16LE.decode(data.as_slice(), DecoderTrap::Replace).ok();
-                } else {
-                    println!("No valid BOM and no default mode.");
-                    return None;
-                }
-            },
-            "iso-8859-1" => {
-                return ISO_8859_1.decode(data.as_slice(), DecoderTrap::Replace).ok();
-            },
-            "iso-8859-15" => {
-                return ISO_8859_15.decode(data.as_slice(), DecoderTrap::Replace).ok();
-            },
-            "us-ascii" | "ascii" => {
-                return ASCII.decode(data.as_slice(), DecoderTrap::Replace).ok();
-            },
-            _ => {
-                return None;
-            }
-        }
+        // the decoders for documents report no byte positions
+        let text = if encoding.to_lowercase().as_str() == "rtf" {
+            decode_rtf(data.as_slice()).ok()?
+        } else if encoding.to_lowercase().as_str() == "uri-list" {
+            let text = String::from_utf8(data.clone()).ok()?;
+            file_paths(text.as_str()).join("\n")
+        } else {
+            return charset::decode_trapped(encoding.as_str(), data.as_slice(), trap);
+        };
+        return Some(TrappedText {
+            text,
+            invalid_bytes: Vec::new(),
+            char_offsets: None,
+        });
     } else {
         return None;
     }
 }
 
+fn decode_trap(id: Option<&str>) -> DecodeTrap {
+    match id {
+        Some("strict") => return DecodeTrap::Strict,
+        Some("escape") => return DecodeTrap::Escape,
+        _ => return DecodeTrap::Replace,
+    }
+}
+
+/// Number of characters an invalid byte sequence has been replaced with.
+fn replacement_length(trap: DecodeTrap, invalid: &InvalidBytes) -> usize {
+    match trap {
+        DecodeTrap::Strict => return 0,
+        DecodeTrap::Replace => return 1,
+        // `\xNN` per byte
+        DecodeTrap::Escape => return 4 * (invalid.end - invalid.start),
+    }
+}
+
+/// Lists the invalid byte sequences with their offset and bytes, the dropdown is hidden if there are none.
+fn set_invalid_bytes_list(dropdown: &ComboBoxText, invalid_bytes: &[InvalidBytes], data: &[u8]) {
+    dropdown.remove_all();
+    dropdown.append(Some("-"), format!("{} invalid byte sequences", invalid_bytes.len()).as_str());
+    for (index, invalid) in invalid_bytes.iter().enumerate() {
+        let bytes = data[invalid.start..invalid.end].iter().map(|byte| format!("{:02X}", byte)).join(" ");
+        dropdown.append(Some(index.to_string().as_str()), format!("Byte {} (0x{:X}): {}", invalid.start, invalid.start, bytes).as_str());
+    }
+    dropdown.set_active_id(Some("-"));
+    dropdown.set_visible(invalid_bytes.len() > 0);
+}
+
 fn convert_to_string(target: &str, data: &Vec<u8>) -> Option<String> {
     let target = target.to_lowercase();
     println!("convert_to_string: target={}", target.as_str());
@@ -344,8 +702,14 @@ fn convert_to_string(target: &str, data: &Vec<u8>) -> Option<String> {
             return ISO_8859_1.decode(data.as_slice(), DecoderTrap::Replace).ok();
         },
         "text" => {
-            // the "TEXT" target's encoding is chose by the owner application, so we can only guess:
-            return ISO_8859_1.decode(data.as_slice(), DecoderTrap::Replace).ok();
+            // the "TEXT" target's encoding is chosen by the owner application, so we can only guess:
+            return decode_detected(data);
+        },
+        "text/rtf" | "application/rtf" => {
+            return decode_rtf(data.as_slice()).ok();
+        },
+        "x-special/gnome-copied-files" => {
+            return String::from_utf8(data.clone()).ok();
         },
         _ => {
             let content_type = mime::Mime::from_str(target.as_str());
@@ -356,65 +720,134 @@ fn convert_to_string(target: &str, data: &Vec<u8>) -> Option<String> {
                 let mut text: Option<String> = None;
                 if let Some(charset) = charset {
                     println!("unwrapped charset: {:?}", &charset);
-                    match charset.as_str() {
-                        "utf-8" => {
-                            text = String::from_utf8(data.clone()).ok();
-                        },
-                        "utf-16le" => {
-                            text = UTF_16LE.decode(data.as_slice(), DecoderTrap::Replace).ok();
-                        },
-                        "utf-16be" => {
-                            text = UTF_16BE.decode(data.as_slice(), DecoderTrap::Replace).ok();
-                        },
-                        "utf-16" | "unicode" => { // äöüß
-                            let mut mode = 0;
-                            if data.len() >= 2 {
-                                if *data.get(0).unwrap() == 0xFEu8 && *data.get(1).unwrap() == 0xFFu8 {
-                                    mode = 1;
-                                } else if *data.get(0).unwrap() == 0xFFu8 && *data.get(1).unwrap() == 0xFEu8 {
-                                    mode = 2
-                                } else {
-                                    #[cfg(target_endian = "big")] { mode = 1; }
-                                    #[cfg(target_endian = "little")] { mode = 2; }
-                                }
-                            }
-                            if mode == 1 {
-                                text = UTF_16BE.decode(data.as_slice(), DecoderTrap::Replace).ok();
-                            } else if mode == 2 {
-                                text = UTF_16LE.decode(data.as_slice(), DecoderTrap::Replace).ok();
-                            }
-                        },
-                        "iso-8859-1" => {
-                            text = ISO_8859_1.decode(data.as_slice(), DecoderTrap::Replace).ok();
-                        },
-                        "iso-8859-15" => {
-                            text = ISO_8859_15.decode(data.as_slice(), DecoderTrap::Replace).ok();
-                        },
-                        "us-ascii" => {
-                            text = ASCII.decode(data.as_slice(), DecoderTrap::Replace).ok();
-                        },
-                        _ => {}
-                    }
+                    text = charset::decode(charset.as_str(), data.as_slice());
                 } else {
                     if content_type.type_().as_str() == "text" {
-                        text = UTF_8.decode(data.as_slice(), DecoderTrap::Replace).ok();
+                        text = decode_detected(data);
                     }
                 }
 
                 return text;
             } else {
-                return UTF_8.decode(data.as_slice(), DecoderTrap::Replace).ok();
+                return decode_detected(data);
             }
         }
     }
 }
 
-fn contains_control_chars(text: &str) -> bool {
-    for ch in text.chars() {
-        if ch < (32 as char) && ch != '\t' && ch != '\n' && ch != '\r' {
-            return true;
+/// Decodes text without a declared charset in the detected charset, or as UTF-8 with replacement characters.
+fn decode_detected(data: &Vec<u8>) -> Option<String> {
+    if let Some(detected) = detect_charset(data.as_slice()) {
+        if let Some(text) = charset::decode(detected.encoding.as_str(), data.as_slice()) {
+            return Some(text);
+        }
+    }
+    return UTF_8.decode(data.as_slice(), DecoderTrap::Replace).ok();
+}
+
+/// Describes the charset detection next to the encoding dropdown, empty if the target declares its charset.
+fn confidence_text(detected: Option<&DetectedCharset>, encoding: Option<&str>) -> String {
+    if let Some(detected) = detected {
+        let confidence = (detected.confidence * 100.0).round();
+        if encoding == Some(detected.encoding.as_str()) {
+            return format!("Detected ({}%)", confidence);
+        }
+        return format!("Overridden, detected {} ({}%)", detected.encoding, confidence);
+    }
+    return String::new();
+}
+
+fn is_image_target(target: &str) -> bool {
+    return target.to_lowercase().starts_with("image/");
+}
+
+fn load_image(data: &[u8]) -> anyhow::Result<Pixbuf> {
+    let loader = PixbufLoader::new();
+    loader.write(data)?;
+    loader.close()?;
+    return loader.pixbuf().ok_or_else(|| anyhow::Error::msg("unknown image format"));
+}
+
+/// Runs `work` on a worker thread, so slow scripts and commands do not block the GUI, and passes
+/// its result to `on_done` on the GTK main thread.
+fn run_in_background<T, W, D>(work: W, on_done: D)
+    where T: Send + 'static, W: FnOnce() -> T + Send + 'static, D: FnOnce(T) + 'static {
+    let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+    std::thread::spawn(move || {
+        let _ = sender.send(work());
+    });
+    let mut on_done = Some(on_done);
+    receiver.attach(None, move |result| {
+        if let Some(on_done) = on_done.take() {
+            on_done(result);
         }
+        return glib::Continue(false);
+    });
+}
+
+/// Publishes the cleaned text under the profile's output targets, or as GTK's default text targets
+/// if the profile has none. With `preserve_targets`, the original data of all other targets is
+/// published as well. The original data is read from `selection`, the text is written to `output_selection`.
+fn publish_text(text: &str, html: Option<&str>, output_targets: Option<&Vec<OutputTarget>>, preserve_targets: bool,
+                selection: Selection, output_selection: Selection) -> anyhow::Result<()> {
+    if output_targets.is_none() && !preserve_targets {
+        output_selection.clipboard().set_text(text);
+        return Ok(());
+    }
+    let output_targets = output_targets.cloned()
+        .unwrap_or(DEFAULT_TEXT_TARGETS.iter().map(|target| OutputTarget::new(target)).collect());
+    let mut targets = OutputTargetEncoder::from_config(&output_targets)?.encode(text, html)?;
+    if preserve_targets {
+        let mut preserved = read_untouched_targets(selection, &targets, is_replaced_by_text);
+        targets.append(&mut preserved);
+    }
+    if !set_clipboard_targets(output_selection, targets) {
+        return Err(anyhow::Error::msg("Could not set the clipboard targets."));
     }
+    return Ok(());
+}
 
-    return false;
-}
\ No newline at end of file
+/// Reads the original data of all clipboard targets which are neither published with the cleaned
+/// data nor replaced by it.
+fn read_untouched_targets<F: Fn(&str) -> bool>(selection: Selection, published: &[(String, Vec<u8>)], is_replaced: F) -> Vec<(String, Vec<u8>)> {
+    let clipboard = selection.clipboard();
+    let mut untouched: Vec<(String, Vec<u8>)> = Vec::new();
+    for target in clipboard.wait_for_targets().unwrap_or(Vec::new()) {
+        let target_name = target.name().to_string();
+        if is_replaced(target_name.as_str()) || published.iter().any(|(name, _data)| name == &target_name) {
+            continue;
+        }
+        if let Some(content) = clipboard.wait_for_contents(&target) {
+            println!("preserving target {}", &target_name);
+            untouched.push((target_name, content.data()));
+        }
+    }
+    return untouched;
+}
+
+/// Publishes the given targets with their raw data on the clipboard.
+fn set_clipboard_targets(selection: Selection, targets: Vec<(String, Vec<u8>)>) -> bool {
+    let entries = targets.iter().enumerate()
+        .map(|(index, (name, _data))| TargetEntry::new(name.as_str(), TargetFlags::empty(), index as u32))
+        .collect::<Vec<TargetEntry>>();
+    let clipboard = selection.clipboard();
+    return clipboard.set_with_data(&entries, move |_clipboard, selection_data, info| {
+        if let Some((name, data)) = targets.get(info as usize) {
+            selection_data.set(&gdk::Atom::intern(name.as_str()), 8, data.as_slice());
+        }
+    });
+}
+
+/// Reads a target of the clipboard as text, e.g. `text/html` for profiles with a `source_target`.
+fn read_target(selection: Selection, target_name: &str) -> Option<String> {
+    let target = gdk::Atom::intern(target_name);
+    let content = selection.clipboard().wait_for_contents(&target)?;
+    let data = content.data();
+    // some applications (e.g. Firefox) offer text/html as UTF-16 with byte order mark
+    let text = if data.starts_with(&[0xFFu8, 0xFEu8]) || data.starts_with(&[0xFEu8, 0xFFu8]) {
+        convert_to_encoding(Some("utf-16".to_string()), &data, DecodeTrap::Replace).map(|decoded| decoded.text)
+    } else {
+        convert_to_string(target_name, &data)
+    };
+    return text.map(|text| text.trim_start_matches('\u{feff}').to_string());
+}
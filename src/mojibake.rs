@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+use crate::charset;
+use crate::config::MojibakeRepair;
+use crate::transformer::Transformer;
+
+/// Encodings UTF-8 is most often wrongly decoded with, Latin-1 is covered by windows-1252.
+const DEFAULT_ENCODINGS: [&str; 1] = ["windows-1252"];
+/// UTF-8 that has been decoded wrongly twice is common, more rounds are not.
+const DEFAULT_MAX_ROUNDS: usize = 2;
+
+/// Text with repaired mojibake and the distinct repaired words, e.g. `("FranÃ§ois", "François")`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepairedText {
+    pub text: String,
+    pub repairs: Vec<(String, String)>,
+}
+
+/// Repairs UTF-8 text which has been decoded as Latin-1 or windows-1252 (e.g. `Ã¤` instead of `ä`),
+/// possibly several times. Only the character sequences forming valid UTF-8 when encoded in the
+/// wrong encoding are re-decoded, and only if this reduces the weirdness of the text.
+#[derive(Debug, Clone)]
+pub struct MojibakeRepairer {
+    /// The encodings with the byte of each of their non-ASCII characters.
+    tables: Vec<(String, HashMap<char, u8>)>,
+    max_rounds: usize,
+}
+
+impl MojibakeRepairer {
+    pub fn new() -> MojibakeRepairer {
+        return MojibakeRepairer {
+            tables: DEFAULT_ENCODINGS.iter()
+                .map(|encoding| (encoding.to_string(), byte_table(encoding).unwrap()))
+                .collect(),
+            max_rounds: DEFAULT_MAX_ROUNDS,
+        };
+    }
+
+    pub fn from_config(trafo_config: &MojibakeRepair) -> anyhow::Result<MojibakeRepairer> {
+        let mut tables: Vec<(String, HashMap<char, u8>)> = Vec::new();
+        let encodings = trafo_config.encodings().clone()
+            .unwrap_or_else(|| DEFAULT_ENCODINGS.iter().map(|encoding| encoding.to_string()).collect());
+        for encoding in encodings.iter() {
+            let encoding = charset::canonical_name(encoding.as_str())
+                .ok_or_else(|| anyhow::Error::msg(format!("Unknown encoding \"{}\".", encoding)))?;
+            let table = byte_table(encoding.as_str())
+                .ok_or_else(|| anyhow::Error::msg(format!("Mojibake can only be repaired for single-byte encodings, not \"{}\".", encoding)))?;
+            tables.push((encoding, table));
+        }
+        if tables.len() == 0 {
+            return Err(anyhow::Error::msg("Mojibake repair needs at least one encoding."));
+        }
+        return Ok(MojibakeRepairer {
+            tables,
+            max_rounds: trafo_config.max_rounds().unwrap_or(DEFAULT_MAX_ROUNDS),
+        });
+    }
+
+    /// Returns the repaired text, or `None` if the text does not look like mojibake.
+    pub fn repair(&self, text: &str) -> Option<RepairedText> {
+        let mut current = text.to_string();
+        for _ in 0..self.max_rounds {
+            let best = self.tables.iter()
+                .filter_map(|(_encoding, table)| {
+                    let repaired = repair_sequences(current.as_str(), table)?;
+                    let repaired_weirdness = weirdness(repaired.as_str(), table);
+                    // only fixes which at least halve the weirdness are applied
+                    if repaired_weirdness * 2 > weirdness(current.as_str(), table) {
+                        return None;
+                    }
+                    return Some((repaired_weirdness, repaired));
+                })
+                .min_by_key(|(weirdness, _repaired)| *weirdness);
+            match best {
+                Some((_weirdness, repaired)) => current = repaired,
+                None => break,
+            }
+        }
+        if current.as_str() == text {
+            return None;
+        }
+        // ASCII characters are never part of a repaired sequence, so the words still correspond
+        let mut repairs: Vec<(String, String)> = Vec::new();
+        for (word, repaired_word) in text.split_ascii_whitespace().zip(current.split_ascii_whitespace()) {
+            let repair = (word.to_string(), repaired_word.to_string());
+            if word != repaired_word && !repairs.contains(&repair) {
+                repairs.push(repair);
+            }
+        }
+        return Some(RepairedText {
+            text: current,
+            repairs,
+        });
+    }
+}
+
+impl Default for MojibakeRepairer {
+    fn default() -> Self {
+        return MojibakeRepairer::new();
+    }
+}
+
+impl Transformer for MojibakeRepairer {
+    fn execute(&self, text: &str) -> anyhow::Result<String> {
+        return Ok(self.repair(text).map(|repaired| repaired.text).unwrap_or(text.to_string()));
+    }
+}
+
+/// Maps the characters of the bytes 0x80 to 0xFF of a single-byte encoding to their byte. The C1
+/// control characters Latin-1 decodes the bytes 0x80 to 0x9F to are mapped as well, which is how
+/// these bytes survive in mojibake created by Latin-1 or by undefined bytes (e.g. 0x81 in windows-1252).
+fn byte_table(encoding: &str) -> Option<HashMap<char, u8>> {
+    let mut table: HashMap<char, u8> = HashMap::new();
+    for byte in 0x80..=0xFFu8 {
+        let decoded = charset::decode(encoding, &[byte])?;
+        let mut chars = decoded.chars();
+        match (chars.next(), chars.next()) {
+            (Some(ch), None) if ch != '\u{fffd}' => {
+                table.insert(ch, byte);
+            }
+            _ => {}
+        }
+    }
+    for byte in 0x80..=0x9Fu8 {
+        table.entry(char::from(byte)).or_insert(byte);
+    }
+    // multi-byte encodings leave most single bytes undefined
+    if table.keys().filter(|ch| **ch as u32 >= 0xA0).count() < 64 {
+        return None;
+    }
+    return Some(table);
+}
+
+fn to_byte(ch: char, table: &HashMap<char, u8>) -> Option<u8> {
+    if ch.is_ascii() {
+        return Some(ch as u8);
+    }
+    return table.get(&ch).copied();
+}
+
+/// Re-decodes every run of characters whose bytes in the wrong encoding form a UTF-8 sequence of
+/// non-ASCII characters. Returns `None` if nothing has been repaired.
+fn repair_sequences(text: &str, table: &HashMap<char, u8>) -> Option<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut result = String::new();
+    let mut repaired_any = false;
+    let mut index = 0;
+    while index < chars.len() {
+        if let Some((length, repaired)) = utf8_sequence(&chars[index..], table) {
+            result.push(repaired);
+            repaired_any = true;
+            index += length;
+        } else {
+            result.push(chars[index]);
+            index += 1;
+        }
+    }
+    if !repaired_any {
+        return None;
+    }
+    return Some(result);
+}
+
+/// Decodes the UTF-8 sequence the bytes of the first characters form, returning its length in
+/// characters and the decoded character. Sequences decoding to control characters are ignored,
+/// except for C1 control characters, which are undefined bytes of an earlier wrong decoding.
+fn utf8_sequence(chars: &[char], table: &HashMap<char, u8>) -> Option<(usize, char)> {
+    let lead = to_byte(*chars.first()?, table)?;
+    let length = match lead {
+        0xC2..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF4 => 4,
+        _ => return None,
+    };
+    let bytes = chars.get(..length)?.iter()
+        .map(|ch| to_byte(*ch, table))
+        .collect::<Option<Vec<u8>>>()?;
+    let decoded = std::str::from_utf8(bytes.as_slice()).ok()?;
+    let ch = decoded.chars().next()?;
+    if ch.is_control() && !('\u{80}'..='\u{9f}').contains(&ch) {
+        return None;
+    }
+    return Some((length, ch));
+}
+
+/// Counts what looks like mojibake: C1 control characters and pairs of characters encoding to a
+/// UTF-8 lead byte followed by a continuation byte in the wrong encoding, like `Ã¤` or `â€`.
+fn weirdness(text: &str, table: &HashMap<char, u8>) -> usize {
+    let mut count = 0;
+    let mut previous: Option<u8> = None;
+    for ch in text.chars() {
+        if ('\u{80}'..='\u{9f}').contains(&ch) {
+            count += 1;
+        }
+        let byte = if ch.is_ascii() { None } else { to_byte(ch, table) };
+        if let (Some(0xC2..=0xF4), Some(0x80..=0xBF)) = (previous, byte) {
+            count += 1;
+        }
+        previous = byte;
+    }
+    return count;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decodes the UTF-8 bytes of the text in the wrong encoding.
+    fn garble(text: &str, encoding: &str) -> String {
+        return charset::decode(encoding, text.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn repairs_wrongly_decoded_utf8() {
+        let repairer = MojibakeRepairer::new();
+        for text in ["François läuft über die Straße", "“quoted” — dash", "£5 😀"] {
+            let garbled = garble(text, "windows-1252");
+            assert_eq!(repairer.repair(garbled.as_str()).unwrap().text, text);
+            // decoded wrongly twice
+            let garbled = garble(garbled.as_str(), "windows-1252");
+            assert_eq!(repairer.repair(garbled.as_str()).unwrap().text, text);
+        }
+    }
+
+    #[test]
+    fn lists_repaired_words() {
+        let repaired = MojibakeRepairer::new().repair("FranÃ§ois and FranÃ§ois lÃ¤uft").unwrap();
+        assert_eq!(repaired.repairs, vec![("FranÃ§ois".to_string(), "François".to_string()),
+                                          ("lÃ¤uft".to_string(), "läuft".to_string())]);
+    }
+
+    #[test]
+    fn keeps_correct_text() {
+        let repairer = MojibakeRepairer::new();
+        assert_eq!(repairer.repair("correct äöü text, naïve café"), None);
+        assert_eq!(repairer.repair("plain ascii"), None);
+    }
+
+    #[test]
+    fn repairs_configured_encodings() {
+        let trafo_config: MojibakeRepair = serde_yaml::from_str("{ encodings: [cp1251] }").unwrap();
+        let repairer = MojibakeRepairer::from_config(&trafo_config).unwrap();
+        assert_eq!(repairer.execute(garble("Привет", "windows-1251").as_str()).unwrap(), "Привет");
+        let trafo_config: MojibakeRepair = serde_yaml::from_str("{ encodings: [shift_jis] }").unwrap();
+        assert!(MojibakeRepairer::from_config(&trafo_config).is_err());
+    }
+}
@@ -0,0 +1,232 @@
+use crate::charset;
+use crate::config::{OutputTarget, UnencodableAction};
+use crate::html_sanitizer::escape_html;
+use crate::text_transformation::SimpleTransformationAction;
+
+/// Targets the sanitized HTML and its plain text are published under if the profile has no `output_targets`.
+pub const SANITIZED_HTML_TARGETS: [&str; 4] = ["text/html", "UTF8_STRING", "text/plain;charset=utf-8", "text/plain"];
+/// The text targets GTK publishes for `Clipboard::set_text`, except `COMPOUND_TEXT`.
+pub const DEFAULT_TEXT_TARGETS: [&str; 5] = ["UTF8_STRING", "STRING", "TEXT", "text/plain;charset=utf-8", "text/plain"];
+/// Targets which describe the clipboard itself instead of its content.
+const META_TARGETS: [&str; 6] = ["TARGETS", "MULTIPLE", "TIMESTAMP", "SAVE_TARGETS", "DELETE", "INSERT_SELECTION"];
+
+/// How characters a target's charset cannot encode are handled, with parsed escape patterns.
+#[derive(Debug, Clone)]
+enum Unencodable {
+    Fail,
+    Replace,
+    Transliterate,
+    Escape(SimpleTransformationAction),
+}
+
+#[derive(Debug, Clone)]
+struct EncodedTarget {
+    target: String,
+    charset: String,
+    bom: Option<&'static [u8]>,
+    unencodable: Unencodable,
+}
+
+/// Encodes the cleaned text for each output target of a profile.
+#[derive(Debug, Clone)]
+pub struct OutputTargetEncoder {
+    targets: Vec<EncodedTarget>,
+}
+
+impl OutputTargetEncoder {
+    pub fn from_config(output_targets: &[OutputTarget]) -> anyhow::Result<OutputTargetEncoder> {
+        let mut targets: Vec<EncodedTarget> = Vec::new();
+        for output_target in output_targets.iter() {
+            let charset = match output_target.charset() {
+                Some(charset) => charset::canonical_name(charset.as_str())
+                    .ok_or_else(|| anyhow::Error::msg(format!("Unknown charset \"{}\" of output target \"{}\".", charset, output_target.target())))?,
+                None => charset::target_charset(output_target.target())
+                    .ok_or_else(|| anyhow::Error::msg(format!("Unknown charset of output target \"{}\".", output_target.target())))?,
+            };
+            if charset::encode(charset.as_str(), "").is_none() {
+                return Err(anyhow::Error::msg(format!("Unknown charset of output target \"{}\".", output_target.target())));
+            }
+            let bom = if output_target.bom() {
+                let bom = charset::byte_order_mark(charset.as_str())
+                    .ok_or_else(|| anyhow::Error::msg(format!("The charset {} of output target \"{}\" has no byte order mark.", charset, output_target.target())))?;
+                // utf-16 is always encoded with its byte order mark
+                if charset == "utf-16" { None } else { Some(bom) }
+            } else {
+                None
+            };
+            let unencodable = match output_target.unencodable() {
+                UnencodableAction::Fail => Unencodable::Fail,
+                UnencodableAction::Replace => Unencodable::Replace,
+                UnencodableAction::Transliterate => Unencodable::Transliterate,
+                UnencodableAction::Escape(pattern) => Unencodable::Escape(SimpleTransformationAction::from_str(pattern.as_str())?),
+            };
+            targets.push(EncodedTarget {
+                target: output_target.target().to_string(),
+                charset,
+                bom,
+                unencodable,
+            });
+        }
+        return Ok(OutputTargetEncoder {
+            targets,
+        });
+    }
+
+    /// Returns the data of each target. Characters which cannot be encoded in a target's charset are
+    /// handled as configured per target. HTML targets get the given HTML, or HTML generated from the text.
+    pub fn encode(&self, text: &str, html: Option<&str>) -> anyhow::Result<Vec<(String, Vec<u8>)>> {
+        let mut result: Vec<(String, Vec<u8>)> = Vec::new();
+        for target in self.targets.iter() {
+            let content = if is_html_target(target.target.as_str()) {
+                let body = html.map(|html| html.to_string()).unwrap_or_else(|| text_to_html(text));
+                if has_charset_declaration(body.as_str()) {
+                    body
+                } else {
+                    format!("<meta charset=\"{}\">{}", target.charset, body)
+                }
+            } else {
+                text.to_string()
+            };
+            let mut data = target.bom.map(|bom| bom.to_vec()).unwrap_or_default();
+            data.append(&mut target.encode(content.as_str())?);
+            result.push((target.target.clone(), data));
+        }
+        return Ok(result);
+    }
+}
+
+impl EncodedTarget {
+    fn encode(&self, text: &str) -> anyhow::Result<Vec<u8>> {
+        let charset = self.charset.as_str();
+        let data = match &self.unencodable {
+            Unencodable::Fail => {
+                if let Some(ch) = charset::first_unencodable(charset, text) {
+                    return Err(anyhow::Error::msg(format!("The character \"{}\" (U+{:04X}) cannot be encoded as {} for target \"{}\".",
+                                                          ch, ch as u32, charset, self.target)));
+                }
+                charset::encode(charset, text)
+            }
+            Unencodable::Replace => charset::encode_replaced(charset, text, |_ch| "?".to_string()),
+            Unencodable::Transliterate => charset::encode_transliterated(charset, text),
+            Unencodable::Escape(action) => charset::encode_replaced(charset, text, |ch| action.execute(ch).unwrap_or_default()),
+        };
+        return data.ok_or_else(|| anyhow::Error::msg(format!("Could not encode the text as {} for target \"{}\".", charset, self.target)));
+    }
+}
+
+/// Returns `true` for targets which hold the (uncleaned) text and are replaced when the cleaned text is
+/// published: plain text, HTML and RTF. Meta targets like `TARGETS` are replaced as well. All other
+/// targets, e.g. images or file lists, can be preserved.
+pub fn is_replaced_by_text(target: &str) -> bool {
+    if is_meta_target(target) {
+        return true;
+    }
+    let target = target.to_lowercase();
+    let mime_type = target.split(';').next().unwrap_or("").trim();
+    match mime_type {
+        "utf8_string" | "string" | "text" | "compound_text" | "text/plain" | "text/html" | "text/rtf" | "application/rtf" | "text/richtext" => return true,
+        _ => return false,
+    }
+}
+
+/// Returns `true` for targets which describe the clipboard itself, like `TARGETS` or `TIMESTAMP`.
+pub fn is_meta_target(target: &str) -> bool {
+    return META_TARGETS.contains(&target);
+}
+
+fn is_html_target(target: &str) -> bool {
+    let target = target.to_lowercase();
+    return target == "text/html" || target.starts_with("text/html;");
+}
+
+/// Returns `true` if the HTML declares its charset in a `<meta charset>` or `<meta http-equiv>` tag.
+fn has_charset_declaration(html: &str) -> bool {
+    let html = html.to_lowercase();
+    return html.match_indices("<meta").any(|(start, _)| {
+        let tag = &html[start..];
+        let tag = &tag[..tag.find('>').unwrap_or(tag.len())];
+        return tag.contains("charset");
+    });
+}
+
+/// Converts plain text into HTML paragraphs, single line breaks become `<br>`.
+pub fn text_to_html(text: &str) -> String {
+    let text = text.replace("\r\n", "\n");
+    return text.split("\n\n")
+        .filter(|paragraph| paragraph.trim().len() > 0)
+        .map(|paragraph| format!("<p>{}</p>", escape_html(paragraph.trim_matches('\n')).replace('\n', "<br>")))
+        .collect::<Vec<String>>()
+        .join("\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(targets: &str, text: &str, html: Option<&str>) -> anyhow::Result<Vec<(String, Vec<u8>)>> {
+        let output_targets: Vec<OutputTarget> = serde_yaml::from_str(targets).unwrap();
+        return OutputTargetEncoder::from_config(&output_targets)?.encode(text, html);
+    }
+
+    #[test]
+    fn writes_a_single_utf16_byte_order_mark() {
+        let encoded = encode("[{ target: text/plain, charset: utf-16, bom: true }]", "ab", None).unwrap();
+        assert_eq!(encoded[0].1, vec![0xFF, 0xFE, b'a', 0, b'b', 0]);
+        let encoded = encode("[{ target: text/plain, charset: utf-16be, bom: true }]", "ab", None).unwrap();
+        assert_eq!(encoded[0].1, vec![0xFE, 0xFF, 0, b'a', 0, b'b']);
+        assert!(encode("[{ target: text/plain, charset: windows-1252, bom: true }]", "ab", None).is_err());
+    }
+
+    #[test]
+    fn handles_unencodable_characters() {
+        let text = "Ä € ✓";
+        assert!(encode("[{ target: STRING, unencodable: fail }]", text, None).is_err());
+        let encoded = encode("[{ target: STRING, unencodable: replace }]", text, None).unwrap();
+        assert_eq!(encoded[0].1, b"\xC4 ? ?".to_vec());
+        let encoded = encode("[{ target: STRING, unencodable: transliterate }]", text, None).unwrap();
+        assert_eq!(encoded[0].1, b"\xC4 EUR OK".to_vec());
+        let encoded = encode("[{ target: text/plain;charset=us-ascii, unencodable: { escape: '<{uni-codepoint}>' } }]", text, None).unwrap();
+        assert_eq!(String::from_utf8(encoded[0].1.clone()).unwrap(), "<U+c4> <U+20ac> <U+2713>");
+    }
+
+    #[test]
+    fn declares_the_charset_of_html_once() {
+        let encoded = encode("[{ target: text/html }]", "a < b", None).unwrap();
+        assert_eq!(String::from_utf8(encoded[0].1.clone()).unwrap(), "<meta charset=\"utf-8\"><p>a &lt; b</p>");
+        let html = "<html><head><META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=utf-8\"></head></html>";
+        let encoded = encode("[{ target: text/html }]", "", Some(html)).unwrap();
+        assert_eq!(String::from_utf8(encoded[0].1.clone()).unwrap(), html);
+    }
+
+    #[test]
+    fn replaces_text_targets_only() {
+        assert!(is_replaced_by_text("text/plain;charset=utf-8"));
+        assert!(is_replaced_by_text("TARGETS"));
+        assert!(!is_replaced_by_text("image/png"));
+        assert!(!is_replaced_by_text("text/uri-list"));
+    }
+
+    #[test]
+    fn encodes_targets_in_their_default_charset() {
+        let encoded = encode("[{ target: UTF8_STRING }, { target: STRING }, { target: text/plain;charset=utf-16le }]", "ä", None).unwrap();
+        let encoded: Vec<(&str, &[u8])> = encoded.iter().map(|(target, data)| (target.as_str(), data.as_slice())).collect();
+        assert_eq!(encoded, vec![("UTF8_STRING", &[0xC3, 0xA4][..]), ("STRING", &[0xE4][..]),
+                                 ("text/plain;charset=utf-16le", &[0xE4, 0x00][..])]);
+        let err = encode("[{ target: text/plain, charset: klingon }]", "ä", None).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown charset \"klingon\" of output target \"text/plain\".");
+        assert!(encode("[{ target: image/png }]", "ä", None).is_err());
+    }
+
+    #[test]
+    fn converts_text_to_html_paragraphs() {
+        assert_eq!(text_to_html("a & b\r\nc\n\n\n<d>\n"), "<p>a &amp; b<br>c</p>\n<p>&lt;d&gt;</p>");
+        assert_eq!(text_to_html("\n\n"), "");
+    }
+
+    #[test]
+    fn keeps_meta_targets_apart_from_content() {
+        assert!(is_meta_target("TIMESTAMP") && is_meta_target("MULTIPLE"));
+        assert!(!is_meta_target("timestamp"));
+        assert!(!is_meta_target("text/plain"));
+    }
+}
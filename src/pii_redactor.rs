@@ -0,0 +1,294 @@
+use std::net::Ipv6Addr;
+use std::str::FromStr;
+use regex::{Captures, Regex};
+use crate::config::{PiiCategory, PiiCategoryRule, PiiRedaction};
+use crate::transformer::Transformer;
+
+#[derive(Debug, Clone)]
+struct PiiRule {
+    category: PiiCategory,
+    template: String,
+    regex: Regex,
+}
+
+#[derive(Debug, Clone)]
+struct PiiMatch {
+    start: usize,
+    end: usize,
+    rule: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct PiiRedactor {
+    rules: Vec<PiiRule>,
+    placeholder: Regex,
+}
+
+impl PiiRedactor {
+    pub fn from_config(trafo_config: &PiiRedaction) -> anyhow::Result<PiiRedactor> {
+        let mut rules: Vec<PiiRule> = Vec::new();
+        for rule in trafo_config.categories().iter() {
+            rules.push(Self::create_rule(rule)?);
+        }
+        return Ok(PiiRedactor {
+            rules,
+            placeholder: Regex::new(r"\{(first|last):(\d+)\}|\{mask\}")?,
+        });
+    }
+
+    fn create_rule(rule: &PiiCategoryRule) -> anyhow::Result<PiiRule> {
+        let pattern = match rule.category() {
+            PiiCategory::Email => r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}\b",
+            PiiCategory::Phone => r"(\+|\(|\b0)[0-9][0-9 ()/.-]{5,}[0-9]\b",
+            PiiCategory::Iban => r"\b[A-Z]{2}[0-9]{2}( ?[A-Z0-9]){11,30}\b",
+            // payment cards start with the major industry identifiers 2 to 6
+            PiiCategory::CreditCard => r"\b[2-6]([ -]?[0-9]){12,18}\b",
+            PiiCategory::Ipv4 => r"\b((25[0-5]|2[0-4][0-9]|1[0-9]{2}|[1-9]?[0-9])\.){3}(25[0-5]|2[0-4][0-9]|1[0-9]{2}|[1-9]?[0-9])\b",
+            // whole words of hex groups, optionally with a dotted IPv4 tail like ::ffff:192.168.0.1
+            PiiCategory::Ipv6 => r"(?i)(\b[0-9a-f]{1,4}:|::)[0-9a-f:.]*[0-9a-f]\b",
+        };
+        return Ok(PiiRule {
+            category: rule.category(),
+            template: rule.template().to_string(),
+            regex: Regex::new(pattern)?,
+        });
+    }
+
+    fn find_all(&self, text: &str) -> Vec<PiiMatch> {
+        let mut matches: Vec<PiiMatch> = Vec::new();
+        for (index, rule) in self.rules.iter().enumerate() {
+            for found in rule.regex.find_iter(text) {
+                if is_valid(rule.category, found.as_str()) {
+                    matches.push(PiiMatch {
+                        start: found.start(),
+                        end: found.end(),
+                        rule: index,
+                    });
+                }
+            }
+        }
+
+        matches.sort_by(|a, b| a.start.cmp(&b.start).then(b.end.cmp(&a.end)));
+        let mut result: Vec<PiiMatch> = Vec::new();
+        for found in matches {
+            if let Some(last) = result.last() {
+                if found.start < last.end {
+                    continue;
+                }
+            }
+            result.push(found);
+        }
+        return result;
+    }
+
+    fn render(&self, template: &str, matched: &str) -> String {
+        let chars: Vec<char> = matched.chars().collect();
+        return self.placeholder.replace_all(template, |captures: &Captures| {
+            if let (Some(position), Some(count)) = (captures.get(1), captures.get(2)) {
+                let count = usize::from_str(count.as_str()).unwrap_or(0).min(chars.len());
+                if position.as_str() == "first" {
+                    return chars[..count].iter().collect::<String>();
+                }
+                return chars[chars.len() - count..].iter().collect::<String>();
+            }
+            return chars.iter()
+                .map(|ch| if ch.is_alphanumeric() { '*' } else { *ch })
+                .collect::<String>();
+        }).to_string();
+    }
+}
+
+impl Transformer for PiiRedactor {
+    fn execute(&self, text: &str) -> anyhow::Result<String> {
+        let mut output = String::new();
+        let mut position = 0;
+        for found in self.find_all(text) {
+            output += &text[position..found.start];
+            output += self.render(self.rules[found.rule].template.as_str(), &text[found.start..found.end]).as_str();
+            position = found.end;
+        }
+        output += &text[position..];
+        return Ok(output);
+    }
+}
+
+/// Validates a regex match to reduce false positives.
+fn is_valid(category: PiiCategory, candidate: &str) -> bool {
+    match category {
+        PiiCategory::Phone => {
+            return is_phone_number(candidate);
+        }
+        PiiCategory::Iban => {
+            return is_valid_iban(candidate);
+        }
+        PiiCategory::CreditCard => {
+            return is_valid_luhn(candidate);
+        }
+        PiiCategory::Ipv6 => {
+            return is_ipv6_address(candidate);
+        }
+        PiiCategory::Email | PiiCategory::Ipv4 => {
+            return true;
+        }
+    }
+}
+
+/// Accepts IPv6 addresses with at least two groups and a digit, so paths like `a::b` or `cafe::bad`
+/// in Rust or C++ code are not taken for addresses.
+fn is_ipv6_address(candidate: &str) -> bool {
+    let groups = candidate.split(':').filter(|group| group.len() > 0).count();
+    return groups >= 2
+        && candidate.chars().any(|ch| ch.is_ascii_digit())
+        && Ipv6Addr::from_str(candidate).is_ok();
+}
+
+/// Accepts international numbers (`+49 30 1234567`, `+1 (555) 123-4567`), numbers with an area code
+/// in parentheses (`(030) 1234567`) and national numbers with a trunk prefix (`0171 1234567`) which
+/// have 7 to 15 digits. Dates (`05.06.2023`, `01/02/2024`) and version numbers (`0.12.3.4567`) are rejected.
+fn is_phone_number(candidate: &str) -> bool {
+    let digits = candidate.chars().filter(|ch| ch.is_ascii_digit()).count();
+    if !(7..=15).contains(&digits) {
+        return false;
+    }
+    // the trunk prefix of international numbers, e.g. +49 (0) 30 1234567
+    let candidate = candidate.replace("(0)", " ");
+    let mut groups: Vec<String> = Vec::new();
+    let mut separators: Vec<char> = Vec::new();
+    let mut group = String::new();
+    for ch in candidate.chars() {
+        if ch.is_ascii_digit() {
+            group.push(ch);
+        } else if group.len() > 0 {
+            groups.push(group.clone());
+            separators.push(ch);
+            group.clear();
+        }
+    }
+    if group.len() > 0 {
+        groups.push(group);
+    }
+    if is_date(&groups, &separators) {
+        return false;
+    }
+    // the country code may have a single digit, all other groups have at least two digits
+    let international = candidate.starts_with('+');
+    return groups.iter().enumerate()
+        .all(|(index, group)| group.len() >= 2 || (index == 0 && international));
+}
+
+/// Checks whether the first three digit groups form a date like `05.06.2023`, `1/2/24` or `2024-01-02`.
+fn is_date(groups: &[String], separators: &[char]) -> bool {
+    if groups.len() < 3 || separators[0] != separators[1] || !['.', '/', '-'].contains(&separators[0]) {
+        return false;
+    }
+    let number = |index: usize| u32::from_str(groups[index].as_str()).unwrap_or(0);
+    if groups[0].len() == 4 && groups[1].len() == 2 && groups[2].len() == 2 {
+        return (1..=12).contains(&number(1)) && (1..=31).contains(&number(2));
+    }
+    if groups[0].len() > 2 || groups[1].len() > 2 || (groups[2].len() != 2 && groups[2].len() != 4) {
+        return false;
+    }
+    let day_month = (1..=31).contains(&number(0)) && (1..=12).contains(&number(1));
+    // US dates put the month first
+    let month_day = separators[0] == '/' && (1..=12).contains(&number(0)) && (1..=31).contains(&number(1));
+    return day_month || month_day;
+}
+
+/// Checks the Luhn checksum used by credit card numbers. Spaces and dashes are ignored.
+pub fn is_valid_luhn(number: &str) -> bool {
+    let digits: Vec<u32> = number.chars().filter_map(|ch| ch.to_digit(10)).collect();
+    if digits.len() < 13 || digits.len() > 19 {
+        return false;
+    }
+    let mut sum = 0;
+    for (index, digit) in digits.iter().rev().enumerate() {
+        if index % 2 == 1 {
+            let doubled = digit * 2;
+            sum += if doubled > 9 { doubled - 9 } else { doubled };
+        } else {
+            sum += digit;
+        }
+    }
+    return sum % 10 == 0;
+}
+
+/// Checks the ISO 13616 mod-97 checksum of an IBAN. Spaces are ignored.
+pub fn is_valid_iban(iban: &str) -> bool {
+    let iban: String = iban.chars().filter(|ch| !ch.is_whitespace()).collect();
+    if iban.len() < 15 || iban.len() > 34 {
+        return false;
+    }
+    // the first four characters are moved to the end, letters are mapped to 10..35
+    let rearranged = iban[4..].chars().chain(iban[..4].chars());
+    let mut remainder: u32 = 0;
+    for ch in rearranged {
+        let value = match ch.to_digit(36) {
+            Some(value) => value,
+            None => return false,
+        };
+        remainder = if value >= 10 {
+            (remainder * 100 + value) % 97
+        } else {
+            (remainder * 10 + value) % 97
+        };
+    }
+    return remainder == 1;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redact(text: &str) -> String {
+        let trafo_config: PiiRedaction = serde_yaml::from_str("{}").unwrap();
+        return PiiRedactor::from_config(&trafo_config).unwrap().execute(text).unwrap();
+    }
+
+    #[test]
+    fn redacts_phone_numbers() {
+        assert_eq!(redact("Call +49 30 1234567 or +1 (555) 123-4567."), "Call [PHONE] or [PHONE].");
+        assert_eq!(redact("Office: (030) 1234567, mobile 0171-1234567"), "Office: [PHONE], mobile [PHONE]");
+        assert_eq!(redact("Paris: 01 23 45 67 89"), "Paris: [PHONE]");
+        assert_eq!(redact("+49 (0) 89 12345678"), "[PHONE]");
+    }
+
+    #[test]
+    fn keeps_dates_and_version_numbers() {
+        assert_eq!(redact("Due on 05.06.2023 at noon"), "Due on 05.06.2023 at noon");
+        assert_eq!(redact("01/02/2024 12:30"), "01/02/2024 12:30");
+        assert_eq!(redact("released 2024-01-02"), "released 2024-01-02");
+        assert_eq!(redact("version 0.12.3.4567"), "version 0.12.3.4567");
+    }
+
+    #[test]
+    fn redacts_whole_ipv6_addresses_only() {
+        assert_eq!(redact("use a::b and cafe::bad, std::fmt"), "use a::b and cafe::bad, std::fmt");
+        assert_eq!(redact("mapped ::ffff:192.168.0.1 here"), "mapped [IPV6] here");
+        assert_eq!(redact("link fe80::1ff:fe23:4567:890a, loopback ::1."), "link [IPV6], loopback ::1.");
+        assert_eq!(redact("at 12:30:45 from 00:1a:2b:3c:4d:5e"), "at 12:30:45 from 00:1a:2b:3c:4d:5e");
+    }
+
+    #[test]
+    fn validates_luhn() {
+        assert!(is_valid_luhn("4111 1111 1111 1111"));
+        assert!(is_valid_luhn("5500-0000-0000-0004"));
+        assert!(!is_valid_luhn("4111 1111 1111 1112"));
+        assert!(!is_valid_luhn("4111"));
+        assert_eq!(redact("card 4111 1111 1111 1111, not 4111 1111 1111 1112"),
+                   "card [CREDIT_CARD], not 4111 1111 1111 1112");
+    }
+
+    #[test]
+    fn validates_iban() {
+        assert!(is_valid_iban("DE89 3704 0044 0532 0130 00"));
+        assert!(is_valid_iban("GB82WEST12345698765432"));
+        assert!(!is_valid_iban("DE89 3704 0044 0532 0130 01"));
+        assert_eq!(redact("IBAN DE89 3704 0044 0532 0130 00."), "IBAN [IBAN].");
+    }
+
+    #[test]
+    fn redacts_emails_and_ip_addresses() {
+        assert_eq!(redact("mail jane.doe@example.com from 192.168.0.1 or 2001:db8::1"),
+                   "mail [EMAIL] from [IPV4] or [IPV6]");
+    }
+}
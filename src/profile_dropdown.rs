@@ -4,11 +4,10 @@ use glib::ToValue;
 use gtk::{Application, ComboBox, ListStore};
 use gtk::prelude::*;
 use parking_lot::RwLock;
-use crate::config::{Config, TransformationProfile};
-use crate::text_transformation::TextTransformation;
-use serde::{ Serialize, Deserialize };
+use clipboard_cleaner::config::{Config, TransformationProfile};
+use clipboard_cleaner::text_transformation::TextTransformation;
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone)]
 pub struct TransformationProfileInfo {
     pub profile: TransformationProfile,
     pub transformation: TextTransformation,
@@ -0,0 +1,251 @@
+use encoding::{DecoderTrap, EncodingRef};
+use encoding::all::WINDOWS_1252;
+use encoding::label::encoding_from_windows_code_page;
+
+/// Destinations whose content is not part of the document text.
+const SKIPPED_DESTINATIONS: [&str; 30] = [
+    "fonttbl", "colortbl", "stylesheet", "info", "pict", "object", "objdata", "header", "headerl",
+    "headerr", "headerf", "footer", "footerl", "footerr", "footerf", "footnote", "fldinst",
+    "themedata", "colorschememapping", "latentstyles", "datastore", "xmlnstbl", "listtable",
+    "listoverridetable", "rsidtbl", "generator", "filetbl", "revtbl", "pgdsctbl", "bkmkstart",
+];
+
+#[derive(Debug, Clone)]
+struct GroupState {
+    /// Number of fallback characters following a `\uN` escape.
+    unicode_skip: usize,
+    skip: bool,
+}
+
+/// Decodes an RTF document (`text/rtf`, `application/rtf`) to plain text.
+///
+/// Supports `\uN` Unicode escapes, `\'hh` bytes in the document's code page (`\ansicpg`) and
+/// skips non-text destinations like font tables, pictures and `\*` destinations.
+pub fn decode_rtf(data: &[u8]) -> anyhow::Result<String> {
+    if !data.starts_with(b"{\\rtf") {
+        return Err(anyhow::Error::msg("Not an RTF document."));
+    }
+
+    let mut decoder = RtfDecoder {
+        output: String::new(),
+        pending_bytes: Vec::new(),
+        code_page: WINDOWS_1252,
+        fallback_chars: 0,
+        high_surrogate: None,
+    };
+    let mut state = GroupState {
+        unicode_skip: 1,
+        skip: false,
+    };
+    let mut stack: Vec<GroupState> = Vec::new();
+    let mut position = 0;
+    while position < data.len() {
+        let byte = data[position];
+        position += 1;
+        match byte {
+            b'{' => {
+                stack.push(state.clone());
+                decoder.fallback_chars = 0;
+            }
+            b'}' => {
+                if let Some(outer) = stack.pop() {
+                    state = outer;
+                }
+                decoder.fallback_chars = 0;
+            }
+            b'\r' | b'\n' => {}
+            b'\\' => {
+                let next = match data.get(position) {
+                    Some(next) => *next,
+                    None => break,
+                };
+                position += 1;
+                if next.is_ascii_alphabetic() {
+                    let start = position - 1;
+                    while position < data.len() && data[position].is_ascii_alphabetic() {
+                        position += 1;
+                    }
+                    let word = String::from_utf8_lossy(&data[start..position]).to_string();
+                    let parameter_start = position;
+                    if position < data.len() && data[position] == b'-' {
+                        position += 1;
+                    }
+                    while position < data.len() && data[position].is_ascii_digit() {
+                        position += 1;
+                    }
+                    let parameter = String::from_utf8_lossy(&data[parameter_start..position]).parse::<i64>().ok();
+                    if position < data.len() && data[position] == b' ' {
+                        position += 1;
+                    }
+                    decoder.control_word(word.as_str(), parameter, &mut state);
+                } else if next == b'\'' {
+                    let hex = data.get(position..position + 2)
+                        .and_then(|hex| u8::from_str_radix(String::from_utf8_lossy(hex).as_ref(), 16).ok());
+                    position += 2;
+                    if let Some(hex) = hex {
+                        if !state.skip {
+                            decoder.push_byte(hex);
+                        }
+                    }
+                } else if !state.skip {
+                    match next {
+                        b'*' => state.skip = true,
+                        b'~' => decoder.push_char('\u{a0}'),
+                        b'_' => decoder.push_char('\u{2011}'),
+                        b'-' => {}
+                        b'\r' | b'\n' => decoder.push_char('\n'),
+                        _ => decoder.push_char(next as char),
+                    }
+                } else if next == b'*' {
+                    state.skip = true;
+                }
+            }
+            _ => {
+                if !state.skip {
+                    decoder.push_byte(byte);
+                }
+            }
+        }
+    }
+    decoder.flush();
+    return Ok(decoder.output);
+}
+
+struct RtfDecoder {
+    output: String,
+    /// Bytes in the document code page, decoded together to support multi-byte code pages.
+    pending_bytes: Vec<u8>,
+    code_page: EncodingRef,
+    fallback_chars: usize,
+    high_surrogate: Option<u32>,
+}
+
+impl RtfDecoder {
+    fn control_word(&mut self, word: &str, parameter: Option<i64>, state: &mut GroupState) {
+        if word == "ansicpg" {
+            if let Some(code_page) = parameter.and_then(|code_page| encoding_from_windows_code_page(code_page as usize)) {
+                self.code_page = code_page;
+            }
+            return;
+        }
+        if word == "uc" {
+            state.unicode_skip = parameter.unwrap_or(1).max(0) as usize;
+            return;
+        }
+        if SKIPPED_DESTINATIONS.contains(&word) {
+            state.skip = true;
+        }
+        if state.skip {
+            return;
+        }
+        if self.fallback_chars > 0 {
+            self.fallback_chars -= 1;
+            return;
+        }
+        match word {
+            "u" => {
+                if let Some(code) = parameter {
+                    // values above 32767 are written as negative numbers
+                    let code = if code < 0 { code + 65536 } else { code };
+                    self.push_unicode(code as u32);
+                    self.fallback_chars = state.unicode_skip;
+                }
+            }
+            "par" | "line" | "sect" | "page" | "row" => self.push_char('\n'),
+            "tab" | "cell" => self.push_char('\t'),
+            "emdash" => self.push_char('\u{2014}'),
+            "endash" => self.push_char('\u{2013}'),
+            "bullet" => self.push_char('\u{2022}'),
+            "lquote" => self.push_char('\u{2018}'),
+            "rquote" => self.push_char('\u{2019}'),
+            "ldblquote" => self.push_char('\u{201c}'),
+            "rdblquote" => self.push_char('\u{201d}'),
+            _ => {}
+        }
+    }
+
+    fn push_byte(&mut self, byte: u8) {
+        if self.fallback_chars > 0 {
+            self.fallback_chars -= 1;
+            return;
+        }
+        self.pending_bytes.push(byte);
+    }
+
+    fn push_char(&mut self, ch: char) {
+        if self.fallback_chars > 0 {
+            self.fallback_chars -= 1;
+            return;
+        }
+        self.flush();
+        self.output.push(ch);
+    }
+
+    fn push_unicode(&mut self, code: u32) {
+        self.flush_bytes();
+        // characters outside of the BMP are written as two escapes for the UTF-16 surrogate pair
+        if (0xD800..0xDC00).contains(&code) {
+            self.high_surrogate = Some(code);
+            return;
+        }
+        if let Some(high) = self.high_surrogate.take() {
+            if (0xDC00..0xE000).contains(&code) {
+                let combined = 0x10000 + ((high - 0xD800) << 10) + (code - 0xDC00);
+                self.output.push(char::from_u32(combined).unwrap_or('\u{fffd}'));
+                return;
+            }
+            self.output.push('\u{fffd}');
+        }
+        self.output.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+    }
+
+    fn flush(&mut self) {
+        if self.high_surrogate.take().is_some() {
+            self.output.push('\u{fffd}');
+        }
+        self.flush_bytes();
+    }
+
+    fn flush_bytes(&mut self) {
+        if self.pending_bytes.len() > 0 {
+            let text = self.code_page.decode(self.pending_bytes.as_slice(), DecoderTrap::Replace)
+                .unwrap_or_else(|_| String::from_utf8_lossy(self.pending_bytes.as_slice()).to_string());
+            self.output += text.as_str();
+            self.pending_bytes.clear();
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_text_and_code_page_bytes() {
+        let rtf = br##"{\rtf1\ansi\ansicpg1252\deff0{\fonttbl{\f0\fswiss Arial;}}{\colortbl;\red0\green0\blue0;}{\*\generator Riched20;}
+\pard\f0\fs20 Gr\'fc\'dfe {\b bold}\par
+Tab\tab x\line end\par}"##;
+        assert_eq!(decode_rtf(rtf).unwrap(), "Grüße bold\nTab\tx\nend\n");
+        assert_eq!(decode_rtf(br"{\rtf1\ansi\ansicpg1251 \'cf\'f0\'e8\par}").unwrap(), "При\n");
+    }
+
+    #[test]
+    fn decodes_unicode_escapes() {
+        assert_eq!(decode_rtf(br"{\rtf1 \u8364? and {\uc2\u20320\'93\'94} quoted\par}").unwrap(), "€ and 你 quoted\n");
+        // surrogate pairs are written as negative numbers
+        assert_eq!(decode_rtf(br"{\rtf1 \u-10179?\u-8704?\par}").unwrap(), "😀\n");
+    }
+
+    #[test]
+    fn skips_destinations_and_pictures() {
+        let rtf = br##"{\rtf1{\info{\title x}}{\field{\*\fldinst HYPERLINK "https://x"}{\fldrslt link}}\par
+{\pict\wmetafile8 0102ABCDEF}\'e4\par}"##;
+        assert_eq!(decode_rtf(rtf).unwrap(), "link\nä\n");
+    }
+
+    #[test]
+    fn rejects_other_data() {
+        assert!(decode_rtf(b"plain").is_err());
+    }
+}
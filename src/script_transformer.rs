@@ -0,0 +1,174 @@
+use std::time::{Duration, Instant};
+use rhai::{Dynamic, Engine, EvalAltResult, Scope, AST};
+use rhai::module_resolvers::DummyModuleResolver;
+use crate::config::{Config, ScriptScope, ScriptTransformation};
+use crate::transformer::Transformer;
+use serde::{ Serialize, Deserialize };
+
+pub const DEFAULT_MAX_OPERATIONS: u64 = 1_000_000;
+pub const DEFAULT_TIMEOUT_MS: u64 = 2_000;
+pub const MAX_OPERATIONS: u64 = 1_000_000_000;
+pub const MAX_TIMEOUT_MS: u64 = 60_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScriptTransformer {
+    source: String,
+    scope: ScriptScope,
+    max_operations: u64,
+    timeout: Duration,
+}
+
+impl ScriptTransformer {
+    pub fn from_config(config: &Config, trafo_config: &ScriptTransformation) -> anyhow::Result<ScriptTransformer> {
+        let source = match (trafo_config.source(), trafo_config.file()) {
+            (Some(source), None) => source.clone(),
+            (None, Some(file)) => {
+                let path = config.resolve_path(file.as_str());
+                std::fs::read_to_string(&path).or_else(|err| Err(anyhow::Error::msg(
+                    format!("Could not read script file {:?}: {}", &path, err))))?
+            }
+            _ => {
+                return Err(anyhow::Error::msg("A script transformation needs either a source or a file."));
+            }
+        };
+
+        // rhai treats 0 operations as unlimited
+        let max_operations = trafo_config.max_operations().unwrap_or(DEFAULT_MAX_OPERATIONS);
+        if !(1..=MAX_OPERATIONS).contains(&max_operations) {
+            return Err(anyhow::Error::msg(format!(
+                "max_operations of a script must be between 1 and {}, not {}.", MAX_OPERATIONS, max_operations)));
+        }
+        let timeout_ms = trafo_config.timeout_ms().unwrap_or(DEFAULT_TIMEOUT_MS);
+        if !(1..=MAX_TIMEOUT_MS).contains(&timeout_ms) {
+            return Err(anyhow::Error::msg(format!(
+                "timeout_ms of a script must be between 1 and {}, not {}.", MAX_TIMEOUT_MS, timeout_ms)));
+        }
+
+        let result = ScriptTransformer {
+            source,
+            scope: trafo_config.scope().clone(),
+            max_operations,
+            timeout: Duration::from_millis(timeout_ms),
+        };
+        // compile once to report syntax errors when the profile is loaded
+        result.compile(&result.create_engine(Instant::now()))?;
+        return Ok(result);
+    }
+
+    /// Runs the script with the given input variable. Returns `None` if the script returns `()`.
+    fn run(&self, engine: &Engine, ast: &AST, name: &str, value: Dynamic) -> anyhow::Result<Option<String>> {
+        let mut scope = Scope::new();
+        scope.push_dynamic(name, value);
+        let result = engine.eval_ast_with_scope::<Dynamic>(&mut scope, ast)
+            .or_else(|err| match *err {
+                EvalAltResult::ErrorTerminated(_, _) => Err(anyhow::Error::msg(format!(
+                    "Script exceeded the time limit of {} ms.", self.timeout.as_millis()))),
+                EvalAltResult::ErrorTooManyOperations(_) => Err(anyhow::Error::msg(format!(
+                    "Script exceeded the limit of {} operations.", self.max_operations))),
+                err => Err(anyhow::Error::msg(format!("Script error: {}", err))),
+            })?;
+
+        if result.is_unit() {
+            return Ok(None);
+        } else if result.is_char() {
+            return Ok(Some(result.as_char().unwrap().to_string()));
+        } else if result.is_string() {
+            return Ok(Some(result.into_string().unwrap()));
+        }
+        return Err(anyhow::Error::msg(format!(
+            "Script must return a string, a character or (), but returned {}.", result.type_name())));
+    }
+
+    fn compile(&self, engine: &Engine) -> anyhow::Result<AST> {
+        return engine.compile(self.source.as_str())
+            .or_else(|err| Err(anyhow::Error::msg(format!("Syntax error in script: {}", err))));
+    }
+
+    /// Creates a sandboxed engine: no module imports, no `eval` and limited resources.
+    fn create_engine(&self, deadline: Instant) -> Engine {
+        let mut engine = Engine::new();
+        engine.set_module_resolver(DummyModuleResolver::new());
+        engine.disable_symbol("eval");
+        engine.set_max_operations(self.max_operations);
+        engine.set_max_call_levels(64);
+        engine.set_max_expr_depths(64, 32);
+        engine.set_max_string_size(16 * 1024 * 1024);
+        engine.set_max_array_size(1024 * 1024);
+        engine.set_max_map_size(64 * 1024);
+        engine.on_progress(move |_operations| {
+            if Instant::now() > deadline {
+                return Some(Dynamic::from("timeout"));
+            }
+            return None;
+        });
+        return engine;
+    }
+}
+
+impl Transformer for ScriptTransformer {
+    fn execute(&self, text: &str) -> anyhow::Result<String> {
+        // the deadline covers the whole text, even if the script is run per line or character
+        let engine = self.create_engine(Instant::now() + self.timeout);
+        let ast = self.compile(&engine)?;
+
+        match self.scope {
+            ScriptScope::Text => {
+                let result = self.run(&engine, &ast, "text", Dynamic::from(text.to_string()))?;
+                return Ok(result.unwrap_or_default());
+            }
+            ScriptScope::Line => {
+                let mut output = String::new();
+                for line in text.split_inclusive('\n') {
+                    let content = line.trim_end_matches(['\n', '\r']);
+                    let terminator = &line[content.len()..];
+                    let result = self.run(&engine, &ast, "line", Dynamic::from(content.to_string()))?;
+                    if let Some(result) = result {
+                        output += result.as_str();
+                        output += terminator;
+                    }
+                }
+                return Ok(output);
+            }
+            ScriptScope::Char => {
+                let mut output = String::new();
+                for ch in text.chars() {
+                    let result = self.run(&engine, &ast, "ch", Dynamic::from(ch))?;
+                    if let Some(result) = result {
+                        output += result.as_str();
+                    }
+                }
+                return Ok(output);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn script(yaml: &str) -> anyhow::Result<ScriptTransformer> {
+        let config = Config::default();
+        let trafo_config: ScriptTransformation = serde_yaml::from_str(yaml).unwrap();
+        return ScriptTransformer::from_config(&config, &trafo_config);
+    }
+
+    #[test]
+    fn runs_per_line() {
+        let trafo = script("{ source: 'if line == \"\" { () } else { line.to_upper() }', scope: line }").unwrap();
+        assert_eq!(trafo.execute("ab\n\ncd\r\n").unwrap(), "AB\nCD\r\n");
+    }
+
+    #[test]
+    fn rejects_unlimited_operations() {
+        let err = script("{ source: 'text', max_operations: 0 }").unwrap_err();
+        assert!(err.to_string().contains("max_operations"), "{}", err);
+        assert!(script("{ source: 'text', timeout_ms: 3600000 }").is_err());
+    }
+
+    #[test]
+    fn stops_endless_loops() {
+        let trafo = script("{ source: 'loop {}', max_operations: 1000 }").unwrap();
+        assert!(trafo.execute("text").unwrap_err().to_string().contains("1000 operations"));
+    }
+}
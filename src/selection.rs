@@ -0,0 +1,66 @@
+use std::str::FromStr;
+#[cfg(feature = "gui")]
+use gdk::{Atom, SELECTION_CLIPBOARD, SELECTION_PRIMARY, SELECTION_SECONDARY};
+
+/// The X11 selections: CLIPBOARD (Ctrl+C / Ctrl+V), PRIMARY (selected text, pasted with the middle
+/// mouse button) and SECONDARY.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Selection {
+    Clipboard,
+    Primary,
+    Secondary,
+}
+
+impl Selection {
+    pub const ALL: [Selection; 3] = [Selection::Clipboard, Selection::Primary, Selection::Secondary];
+
+    #[cfg(feature = "gui")]
+    pub fn atom(&self) -> Atom {
+        match self {
+            Selection::Clipboard => return SELECTION_CLIPBOARD,
+            Selection::Primary => return SELECTION_PRIMARY,
+            Selection::Secondary => return SELECTION_SECONDARY,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Selection::Clipboard => return "clipboard",
+            Selection::Primary => return "primary",
+            Selection::Secondary => return "secondary",
+        }
+    }
+
+    #[cfg(feature = "gui")]
+    pub fn clipboard(&self) -> gtk::Clipboard {
+        return gtk::Clipboard::get(&self.atom());
+    }
+}
+
+impl FromStr for Selection {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_lowercase().as_str() {
+            "clipboard" => return Ok(Selection::Clipboard),
+            "primary" => return Ok(Selection::Primary),
+            "secondary" => return Ok(Selection::Secondary),
+            _ => return Err(anyhow::Error::msg(format!("Unknown selection \"{}\", expected clipboard, primary or secondary.", name))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_selection_names_case_insensitively() {
+        for selection in Selection::ALL {
+            assert_eq!(Selection::from_str(selection.name()).unwrap(), selection);
+        }
+        assert_eq!(Selection::from_str("PRIMARY").unwrap(), Selection::Primary);
+        let err = Selection::from_str("tertiary").unwrap_err();
+        assert_eq!(err.to_string(), "Unknown selection \"tertiary\", expected clipboard, primary or secondary.");
+    }
+}
@@ -0,0 +1,170 @@
+use regex::Regex;
+use crate::config::CodeSnippetCleanup;
+use crate::transformer::Transformer;
+
+#[derive(Debug, Clone)]
+pub struct SnippetCleaner {
+    prompt: Option<Regex>,
+    drop_output: bool,
+    line_number: Option<Regex>,
+    dedent: bool,
+}
+
+impl SnippetCleaner {
+    pub fn from_config(trafo_config: &CodeSnippetCleanup) -> anyhow::Result<SnippetCleaner> {
+        let prompts = trafo_config.prompts();
+        let prompt = if prompts.len() > 0 {
+            let pattern = format!("^[ \\t]*(?:{})", prompts.iter().map(|prompt| format!("(?:{})", prompt)).collect::<Vec<String>>().join("|"));
+            Some(Regex::new(pattern.as_str())
+                .or_else(|err| Err(anyhow::Error::msg(format!("Invalid prompt pattern: {}", err))))?)
+        } else {
+            None
+        };
+        let line_number = if trafo_config.line_numbers() {
+            // only a single whitespace separator is removed, the code's own indentation is
+            // removed later on by dedenting.
+            Some(Regex::new(r"^[ \t]*([0-9]+)(?:[ \t]*[|:][ \t]?|[ \t]|$)")?)
+        } else {
+            None
+        };
+
+        return Ok(SnippetCleaner {
+            prompt,
+            drop_output: trafo_config.drop_output(),
+            line_number,
+            dedent: trafo_config.dedent(),
+        });
+    }
+
+    /// Removes line numbers if all non-empty lines (at least two) start with consecutive numbers.
+    fn strip_line_numbers(&self, lines: Vec<String>, line_number: &Regex) -> Vec<String> {
+        let mut expected: Option<u64> = None;
+        let mut prefix_lengths: Vec<usize> = Vec::new();
+        for line in lines.iter() {
+            if line.trim().len() == 0 {
+                prefix_lengths.push(0);
+                continue;
+            }
+            let captures = match line_number.captures(line) {
+                Some(captures) => captures,
+                None => return lines,
+            };
+            let number = captures.get(1).unwrap().as_str().parse::<u64>().unwrap_or(0);
+            if expected.is_some() && expected != Some(number) {
+                return lines;
+            }
+            expected = Some(number + 1);
+            prefix_lengths.push(captures.get(0).unwrap().end());
+        }
+        if prefix_lengths.iter().filter(|prefix_length| **prefix_length > 0).count() < 2 {
+            return lines;
+        }
+
+        return lines.iter().zip(prefix_lengths.iter())
+            .map(|(line, prefix_length)| line[*prefix_length..].to_string())
+            .collect();
+    }
+
+    fn strip_prompts(&self, lines: Vec<String>, prompt: &Regex) -> Vec<String> {
+        let has_prompts = lines.iter().any(|line| prompt.is_match(line));
+        let mut result: Vec<String> = Vec::new();
+        for line in lines {
+            if let Some(found) = prompt.find(line.as_str()) {
+                result.push(line[found.end()..].to_string());
+            } else if !(self.drop_output && has_prompts) {
+                result.push(line);
+            }
+        }
+        return result;
+    }
+
+    fn strip_common_indentation(&self, lines: Vec<String>) -> Vec<String> {
+        let mut common: Option<&str> = None;
+        for line in lines.iter() {
+            if line.trim().len() == 0 {
+                continue;
+            }
+            let indentation = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+            common = match common {
+                None => Some(indentation),
+                Some(common) => {
+                    let length = common.bytes().zip(indentation.bytes())
+                        .take_while(|(a, b)| a == b)
+                        .count();
+                    Some(&common[..length])
+                }
+            };
+        }
+
+        let common_length = common.map(|common| common.len()).unwrap_or(0);
+        return lines.iter()
+            .map(|line| if line.trim().len() == 0 {
+                line.trim_start_matches([' ', '\t']).to_string()
+            } else {
+                line[common_length..].to_string()
+            })
+            .collect();
+    }
+}
+
+impl Transformer for SnippetCleaner {
+    fn execute(&self, text: &str) -> anyhow::Result<String> {
+        let (text, terminator) = match text.strip_suffix('\n') {
+            Some(text) => (text, "\n"),
+            None => (text, ""),
+        };
+        let mut lines: Vec<String> = text.split('\n').map(|line| line.to_string()).collect();
+        if let Some(line_number) = &self.line_number {
+            lines = self.strip_line_numbers(lines, line_number);
+        }
+        if let Some(prompt) = &self.prompt {
+            lines = self.strip_prompts(lines, prompt);
+        }
+        if self.dedent {
+            lines = self.strip_common_indentation(lines);
+        }
+        return Ok(lines.join("\n") + terminator);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clean(yaml: &str, text: &str) -> String {
+        let trafo_config: CodeSnippetCleanup = serde_yaml::from_str(yaml).unwrap();
+        return SnippetCleaner::from_config(&trafo_config).unwrap().execute(text).unwrap();
+    }
+
+    #[test]
+    fn strips_prompts_and_output() {
+        let session = "$ ls -a\n.  ..\n$ echo hi\nhi\n";
+        assert_eq!(clean("{}", session), "ls -a\n.  ..\necho hi\nhi\n");
+        assert_eq!(clean("{ drop_output: true }", session), "ls -a\necho hi\n");
+        assert_eq!(clean("{}", ">>> x = 1\n>>> if x:\n...     print(x)\n"), "x = 1\nif x:\n    print(x)\n");
+    }
+
+    #[test]
+    fn keeps_comments_of_snippets_without_prompts() {
+        let python = "# compute the sum\ntotal = a + b\nprint(total)\n";
+        assert_eq!(clean("{}", python), python);
+        assert_eq!(clean("{ drop_output: true }", python), python);
+        let shell = "#!/bin/sh\n# install\napt install foo\n";
+        assert_eq!(clean("{ drop_output: true }", shell), shell);
+        assert_eq!(clean("{ drop_output: true }", "$ cat x.sh\n# install\n$ ls\n"), "cat x.sh\nls\n");
+    }
+
+    #[test]
+    fn strips_consecutive_line_numbers() {
+        assert_eq!(clean("{}", " 9 fn main() {\n10     run();\n11 }\n"), "fn main() {\n    run();\n}\n");
+        assert_eq!(clean("{}", "1: a\n\n2: b"), "a\n\nb");
+        // not consecutive, so the numbers belong to the text
+        assert_eq!(clean("{}", "1 apple\n3 pears\n"), "1 apple\n3 pears\n");
+    }
+
+    #[test]
+    fn removes_common_indentation() {
+        assert_eq!(clean("{}", "    if a:\n        b()\n  \n    c()"), "if a:\n    b()\n\nc()");
+        assert_eq!(clean("{ dedent: false }", "    a\n    b"), "    a\n    b");
+    }
+}
@@ -0,0 +1,199 @@
+use std::fmt::Display;
+use quick_xml::events::Event;
+use quick_xml::{Reader, Writer};
+use serde::Serialize;
+use serde_json::Value;
+use crate::config::{DataAction, DataFormat, StructuredDataTransformation};
+use crate::transformer::Transformer;
+
+/// Pretty-prints, minifies, validates or converts JSON, YAML, XML and TOML.
+#[derive(Debug, Clone)]
+pub struct StructuredDataTransformer {
+    format: DataFormat,
+    action: DataAction,
+    to: DataFormat,
+    indent: usize,
+}
+
+impl StructuredDataTransformer {
+    pub fn from_config(trafo_config: &StructuredDataTransformation) -> anyhow::Result<StructuredDataTransformer> {
+        let to = match (trafo_config.action(), trafo_config.to()) {
+            (DataAction::Convert, None) => {
+                return Err(anyhow::Error::msg("The convert action needs a target format (\"to\")."));
+            }
+            (_, to) => to.unwrap_or(trafo_config.format()),
+        };
+        let format = trafo_config.format();
+        if format != to && (format == DataFormat::Xml || to == DataFormat::Xml) {
+            return Err(anyhow::Error::msg("Conversion from or to XML is not supported."));
+        }
+        return Ok(StructuredDataTransformer {
+            format,
+            action: trafo_config.action(),
+            to,
+            indent: trafo_config.indent(),
+        });
+    }
+
+    fn parse(&self, text: &str) -> anyhow::Result<Value> {
+        match self.format {
+            DataFormat::Json => {
+                return serde_json::from_str(text)
+                    .or_else(|err| Err(syntax_error("JSON", Some((err.line(), err.column())), &err)));
+            }
+            DataFormat::Yaml => {
+                return serde_yaml::from_str(text)
+                    .or_else(|err| Err(syntax_error("YAML", err.location().map(|location| (location.line(), location.column())), &err)));
+            }
+            DataFormat::Toml => {
+                return toml::from_str(text)
+                    .or_else(|err| Err(syntax_error("TOML", err.line_col().map(|(line, column)| (line + 1, column + 1)), &err)));
+            }
+            DataFormat::Xml => {
+                return Err(anyhow::Error::msg("XML cannot be converted."));
+            }
+        }
+    }
+
+    fn write(&self, value: &Value, pretty: bool) -> anyhow::Result<String> {
+        match self.to {
+            DataFormat::Json => {
+                if !pretty {
+                    return Ok(serde_json::to_string(value)?);
+                }
+                let indent = " ".repeat(self.indent);
+                let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+                let mut serializer = serde_json::Serializer::with_formatter(Vec::new(), formatter);
+                value.serialize(&mut serializer)?;
+                return Ok(String::from_utf8(serializer.into_inner())?);
+            }
+            DataFormat::Yaml => {
+                if !pretty {
+                    // JSON is a subset of YAML and the most compact representation
+                    return Ok(serde_json::to_string(value)?);
+                }
+                let yaml = serde_yaml::to_string(value)?;
+                return Ok(yaml.strip_prefix("---\n").unwrap_or(yaml.as_str()).to_string());
+            }
+            DataFormat::Toml => {
+                if !value.is_object() {
+                    return Err(anyhow::Error::msg("Cannot convert to TOML: the document must be a table (object)."));
+                }
+                if contains_null(value) {
+                    return Err(anyhow::Error::msg("Cannot convert to TOML: TOML has no null values."));
+                }
+                let value = toml::Value::try_from(value)
+                    .or_else(|err| Err(anyhow::Error::msg(format!("Cannot convert to TOML: {}", err))))?;
+                if pretty {
+                    return Ok(toml::to_string_pretty(&value)?);
+                }
+                return Ok(toml::to_string(&value)?);
+            }
+            DataFormat::Xml => {
+                return Err(anyhow::Error::msg("Cannot convert to XML."));
+            }
+        }
+    }
+
+    /// Re-indents XML, or removes all whitespace between tags if `pretty` is false.
+    fn reformat_xml(&self, text: &str, pretty: bool) -> anyhow::Result<String> {
+        let mut reader = Reader::from_str(text);
+        reader.trim_text(true);
+        let mut writer = if pretty {
+            Writer::new_with_indent(Vec::new(), b' ', self.indent)
+        } else {
+            Writer::new(Vec::new())
+        };
+        loop {
+            let event = reader.read_event()
+                .or_else(|err| Err(syntax_error("XML", Some(line_and_column(text, reader.buffer_position())), &err)))?;
+            if event == Event::Eof {
+                break;
+            }
+            writer.write_event(event)?;
+        }
+        return Ok(String::from_utf8(writer.into_inner())?);
+    }
+}
+
+impl Transformer for StructuredDataTransformer {
+    fn execute(&self, text: &str) -> anyhow::Result<String> {
+        let terminator = if text.ends_with('\n') { "\n" } else { "" };
+        let pretty = self.action != DataAction::Minify;
+        let result = if self.format == DataFormat::Xml {
+            self.reformat_xml(text, pretty)?
+        } else {
+            self.write(&self.parse(text)?, pretty)?
+        };
+        if self.action == DataAction::Validate {
+            return Ok(text.to_string());
+        }
+        return Ok(result.trim_end().to_string() + terminator);
+    }
+}
+
+fn contains_null(value: &Value) -> bool {
+    match value {
+        Value::Null => return true,
+        Value::Array(values) => return values.iter().any(contains_null),
+        Value::Object(values) => return values.values().any(contains_null),
+        _ => return false,
+    }
+}
+
+/// Creates an error with 1-based line and column, which are removed from the parser's message.
+fn syntax_error(format_name: &str, position: Option<(usize, usize)>, err: &dyn Display) -> anyhow::Error {
+    let message = err.to_string();
+    let message = message.split(" at line ").next().unwrap_or("").to_string();
+    if let Some((line, column)) = position {
+        return anyhow::Error::msg(format!("Invalid {} at line {}, column {}: {}", format_name, line, column, message));
+    }
+    return anyhow::Error::msg(format!("Invalid {}: {}", format_name, message));
+}
+
+fn line_and_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text.as_bytes()[..offset.min(text.len())];
+    let line = before.iter().filter(|byte| **byte == b'\n').count() + 1;
+    let line_start = before.iter().rposition(|byte| *byte == b'\n').map(|index| index + 1).unwrap_or(0);
+    let column = String::from_utf8_lossy(&before[line_start..]).chars().count() + 1;
+    return (line, column);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(yaml: &str, text: &str) -> anyhow::Result<String> {
+        let trafo_config: StructuredDataTransformation = serde_yaml::from_str(yaml).unwrap();
+        return StructuredDataTransformer::from_config(&trafo_config)?.execute(text);
+    }
+
+    #[test]
+    fn formats_json() {
+        assert_eq!(run("{ format: json, action: pretty }", "{\"b\":1,\"a\":[1,{\"x\":null}]}\n").unwrap(),
+                   "{\n  \"b\": 1,\n  \"a\": [\n    1,\n    {\n      \"x\": null\n    }\n  ]\n}\n");
+        assert_eq!(run("{ format: json, action: minify }", "{\n  \"b\": 1,\n  \"a\": [1, 2]\n}\n").unwrap(), "{\"b\":1,\"a\":[1,2]}\n");
+        let err = run("{ format: json, action: validate }", "{\n  \"a\": 1,\n  \"b\" 2\n}").unwrap_err();
+        assert_eq!(err.to_string(), "Invalid JSON at line 3, column 7: expected `:`");
+    }
+
+    #[test]
+    fn converts_between_formats() {
+        assert_eq!(run("{ format: json, action: convert, to: yaml }", "{\"b\":1,\"t\":{\"k\":null}}\n").unwrap(), "b: 1\nt:\n  k: ~\n");
+        assert_eq!(run("{ format: json, action: convert, to: toml }", "{\"t\":{\"k\":\"v\"},\"b\":1}").unwrap(), "b = 1\n\n[t]\nk = 'v'");
+        assert_eq!(run("{ format: yaml, action: convert, to: json, indent: 4 }", "a: 1\nb:\n  - x\n").unwrap(),
+                   "{\n    \"a\": 1,\n    \"b\": [\n        \"x\"\n    ]\n}\n");
+        assert_eq!(run("{ format: toml, action: convert, to: json }", "a = 1\n[t]\nk = \"v\"\n").unwrap(),
+                   "{\n  \"a\": 1,\n  \"t\": {\n    \"k\": \"v\"\n  }\n}\n");
+    }
+
+    #[test]
+    fn formats_xml() {
+        assert_eq!(run("{ format: xml, action: pretty }", "<?xml version=\"1.0\"?><a x=\"1\"><b>text</b><c/></a>").unwrap(),
+                   "<?xml version=\"1.0\"?>\n<a x=\"1\">\n  <b>text</b>\n  <c/>\n</a>");
+        assert_eq!(run("{ format: xml, action: minify }", "<a>\n  <b>text</b>\n</a>\n").unwrap(), "<a><b>text</b></a>\n");
+        assert_eq!(run("{ format: xml, action: minify }", "<a>\n  <b>text</c>\n</a>\n").unwrap_err().to_string(),
+                   "Invalid XML at line 2, column 12: Expecting </b> found </c>");
+        assert!(run("{ format: xml, action: convert, to: json }", "<a/>").is_err());
+    }
+}
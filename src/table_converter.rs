@@ -0,0 +1,226 @@
+use csv::{QuoteStyle, ReaderBuilder, Terminator, WriterBuilder};
+use serde_json::{Map, Value};
+use crate::config::{TableConversion, TableFormat, TableHeader, TableQuoting};
+use crate::transformer::Transformer;
+
+/// Converts delimiter-separated cells, e.g. tab-separated cells copied from a spreadsheet,
+/// into a Markdown table, CSV or JSON.
+#[derive(Debug, Clone)]
+pub struct TableConverter {
+    format: TableFormat,
+    delimiter: u8,
+    unquote: bool,
+    header: TableHeader,
+    output_delimiter: u8,
+    quoting: TableQuoting,
+    quote: u8,
+}
+
+impl TableConverter {
+    pub fn from_config(trafo_config: &TableConversion) -> anyhow::Result<TableConverter> {
+        return Ok(TableConverter {
+            format: trafo_config.format(),
+            delimiter: ascii_byte("delimiter", trafo_config.delimiter())?,
+            unquote: trafo_config.unquote(),
+            header: trafo_config.header(),
+            output_delimiter: ascii_byte("output_delimiter", trafo_config.output_delimiter())?,
+            quoting: trafo_config.quoting(),
+            quote: ascii_byte("quote", trafo_config.quote())?,
+        });
+    }
+
+    fn read_rows(&self, text: &str) -> anyhow::Result<Vec<Vec<String>>> {
+        let mut reader = ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .delimiter(self.delimiter)
+            .quoting(self.unquote)
+            .quote(self.quote)
+            .from_reader(text.as_bytes());
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        for record in reader.records() {
+            let record = record
+                .or_else(|err| Err(anyhow::Error::msg(format!("Could not read table: {}", err))))?;
+            rows.push(record.iter().map(|cell| cell.to_string()).collect());
+        }
+        // rows have the same number of columns, missing cells are empty
+        let columns = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        for row in rows.iter_mut() {
+            row.resize(columns, String::new());
+        }
+        return Ok(rows);
+    }
+
+    fn has_header(&self, rows: &[Vec<String>]) -> bool {
+        match self.header {
+            TableHeader::Present => return rows.len() > 0,
+            TableHeader::Absent => return false,
+            TableHeader::Auto => {
+                if rows.len() < 2 {
+                    return false;
+                }
+                let first = &rows[0];
+                let distinct = first.iter().enumerate()
+                    .all(|(index, cell)| !first[..index].contains(cell));
+                return distinct && first.iter().all(|cell| cell.trim().len() > 0 && !is_numeric(cell));
+            }
+        }
+    }
+
+    fn to_markdown(&self, rows: &[Vec<String>], has_header: bool) -> String {
+        let columns = rows.first().map(|row| row.len()).unwrap_or(0);
+        let cells: Vec<Vec<String>> = rows.iter()
+            .map(|row| row.iter()
+                .map(|cell| cell.trim().replace('|', "\\|").replace("\r\n", "<br>").replace('\n', "<br>"))
+                .collect())
+            .collect();
+        let header = if has_header { cells[0].clone() } else { vec![String::new(); columns] };
+        let body = if has_header { &cells[1..] } else { &cells[..] };
+
+        let widths: Vec<usize> = (0..columns)
+            .map(|column| std::iter::once(&header).chain(body.iter())
+                .map(|row| row[column].chars().count())
+                .max()
+                .unwrap_or(0)
+                .max(3))
+            .collect();
+        // columns with numbers only are right-aligned
+        let numeric: Vec<bool> = (0..columns)
+            .map(|column| body.iter().any(|row| row[column].len() > 0)
+                && body.iter().all(|row| row[column].len() == 0 || is_numeric(&row[column])))
+            .collect();
+
+        let format_row = |row: &[String]| -> String {
+            let cells: Vec<String> = row.iter().enumerate()
+                .map(|(column, cell)| if numeric[column] {
+                    format!("{:>width$}", cell, width = widths[column])
+                } else {
+                    format!("{:<width$}", cell, width = widths[column])
+                })
+                .collect();
+            return format!("| {} |\n", cells.join(" | "));
+        };
+        let mut output = format_row(&header);
+        let separators: Vec<String> = widths.iter().zip(numeric.iter())
+            .map(|(width, numeric)| if *numeric {
+                format!("{}:", "-".repeat(*width - 1))
+            } else {
+                "-".repeat(*width)
+            })
+            .collect();
+        output += format!("| {} |\n", separators.join(" | ")).as_str();
+        for row in body.iter() {
+            output += format_row(row).as_str();
+        }
+        return output;
+    }
+
+    fn to_csv(&self, rows: &[Vec<String>]) -> anyhow::Result<String> {
+        let quote_style = match self.quoting {
+            TableQuoting::Necessary => QuoteStyle::Necessary,
+            TableQuoting::Always => QuoteStyle::Always,
+            TableQuoting::NonNumeric => QuoteStyle::NonNumeric,
+            TableQuoting::Never => QuoteStyle::Never,
+        };
+        let mut writer = WriterBuilder::new()
+            .delimiter(self.output_delimiter)
+            .quote(self.quote)
+            .quote_style(quote_style)
+            .terminator(Terminator::Any(b'\n'))
+            .from_writer(Vec::new());
+        for row in rows.iter() {
+            writer.write_record(row)?;
+        }
+        let data = writer.into_inner()
+            .or_else(|err| Err(anyhow::Error::msg(format!("Could not write CSV: {}", err))))?;
+        return Ok(String::from_utf8(data)?);
+    }
+
+    fn to_json(&self, rows: &[Vec<String>], has_header: bool) -> anyhow::Result<String> {
+        let values: Vec<Value> = if has_header {
+            let keys = &rows[0];
+            rows[1..].iter()
+                .map(|row| Value::Object(keys.iter().zip(row.iter())
+                    .map(|(key, cell)| (key.trim().to_string(), Value::String(cell.clone())))
+                    .collect::<Map<String, Value>>()))
+                .collect()
+        } else {
+            rows.iter()
+                .map(|row| Value::Array(row.iter().map(|cell| Value::String(cell.clone())).collect()))
+                .collect()
+        };
+        return Ok(serde_json::to_string_pretty(&Value::Array(values))? + "\n");
+    }
+}
+
+impl Transformer for TableConverter {
+    fn execute(&self, text: &str) -> anyhow::Result<String> {
+        let rows = self.read_rows(text)?;
+        if rows.len() == 0 {
+            return Ok(text.to_string());
+        }
+        let has_header = self.has_header(&rows);
+        match self.format {
+            TableFormat::Markdown => return Ok(self.to_markdown(&rows, has_header)),
+            TableFormat::Csv => return self.to_csv(&rows),
+            TableFormat::Json => return self.to_json(&rows, has_header),
+        }
+    }
+}
+
+fn ascii_byte(name: &str, ch: char) -> anyhow::Result<u8> {
+    if !ch.is_ascii() {
+        return Err(anyhow::Error::msg(format!("The table {} must be an ASCII character, got '{}'.", name, ch)));
+    }
+    return Ok(ch as u8);
+}
+
+fn is_numeric(cell: &str) -> bool {
+    let cell = cell.trim().trim_end_matches('%');
+    return cell.chars().any(|ch| ch.is_ascii_digit()) && cell.replace(',', "").parse::<f64>().is_ok();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(yaml: &str, text: &str) -> String {
+        let trafo_config: TableConversion = serde_yaml::from_str(yaml).unwrap();
+        return TableConverter::from_config(&trafo_config).unwrap().execute(text).unwrap();
+    }
+
+    const CELLS: &str = "Name\tPrice\tNote\nApple\t1.5\t\"red, \"\"sweet\"\"\nfruit\"\nPear|x\t12\t\n";
+
+    #[test]
+    fn converts_cells_to_markdown() {
+        assert_eq!(convert("{ format: markdown }", CELLS),
+                   "| Name    | Price | Note                  |\n\
+                    | ------- | ----: | --------------------- |\n\
+                    | Apple   |   1.5 | red, \"sweet\"<br>fruit |\n\
+                    | Pear\\|x |    12 |                       |\n");
+        assert_eq!(convert("{ format: markdown }", "1\t2\n3\t4\n"),
+                   "|     |     |\n| --: | --: |\n|   1 |   2 |\n|   3 |   4 |\n");
+    }
+
+    #[test]
+    fn converts_tsv_to_csv() {
+        assert_eq!(convert("{ format: csv }", CELLS),
+                   "Name,Price,Note\nApple,1.5,\"red, \"\"sweet\"\"\nfruit\"\nPear|x,12,\n");
+        assert_eq!(convert("{ format: csv, quoting: non_numeric, output_delimiter: ';' }", "a\t1\nb\t2\n"),
+                   "\"a\";1\n\"b\";2\n");
+    }
+
+    #[test]
+    fn converts_csv_to_json() {
+        assert_eq!(convert("{ format: json, delimiter: ',' }", "name,qty\napple,3\n"),
+                   "[\n  {\n    \"name\": \"apple\",\n    \"qty\": \"3\"\n  }\n]\n");
+        assert_eq!(convert("{ format: json, delimiter: ',', header: absent }", "name,qty\n"),
+                   "[\n  [\n    \"name\",\n    \"qty\"\n  ]\n]\n");
+    }
+
+    #[test]
+    fn rejects_non_ascii_delimiters() {
+        let trafo_config: TableConversion = serde_yaml::from_str("{ format: csv, delimiter: '§' }").unwrap();
+        assert!(TableConverter::from_config(&trafo_config).is_err());
+    }
+}
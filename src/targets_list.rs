@@ -1,24 +1,24 @@
 use std::cell::Cell;
 use std::sync::Arc;
-use gdk::{Atom, SELECTION_CLIPBOARD};
+use gdk::Atom;
 use glib::{ObjectExt, ToValue};
 use gtk::prelude::{GtkListStoreExt, GtkListStoreExtManual, TreeModelExt, TreeSelectionExt, TreeViewColumnExt, TreeViewExt};
 use gtk::{Application, TreeSelection, TreeView};
 use parking_lot::RwLock;
-
-pub const DEFAULT_SELECTION: Atom = SELECTION_CLIPBOARD;
+use clipboard_cleaner::selection::Selection;
 
 pub struct TargetsList {
     app: Arc<Application>,
     treeview: Arc<TreeView>,
+    selection: RwLock<Selection>,
     on_model_change_handler: RwLock<Cell<Box<dyn Fn(&TargetsList) -> () + 'static>>>,
     on_selection_handler: RwLock<Cell<Box<dyn Fn(&TargetsList, &TreeSelection) -> () + 'static>>>,
 }
 
 impl TargetsList {
-    pub fn new(app: Arc<Application>, treeview: Arc<TreeView>) -> Arc<TargetsList> {
+    pub fn new(app: Arc<Application>, treeview: Arc<TreeView>, selection: Selection) -> Arc<TargetsList> {
         println!("creating list store ...");
-        let list_store = create_list_store();
+        let list_store = create_list_store(selection);
         treeview.set_model(Some(&*list_store));
 
         {
@@ -34,6 +34,7 @@ impl TargetsList {
         let result = Arc::new(TargetsList {
             app: app.clone(),
             treeview: treeview.clone(),
+            selection: RwLock::new(selection),
             on_model_change_handler: RwLock::new(Cell::new(Box::new(|_| {}))),
             on_selection_handler: RwLock::new(Cell::new(Box::new(|_, _| {}))),
         });
@@ -44,23 +45,37 @@ impl TargetsList {
             result_clone.fire_selection(selection);
         });
 
-        let result_clone = result.clone();
-        let treeview_clone = result.treeview.clone();
-        gtk::Clipboard::get(&DEFAULT_SELECTION).connect_local("owner-change", true, move |_value| {
-            let result_clone = result_clone.clone();
-            // let treeview_clone = treeview_clone.clone();
-            println!("clipboard changed!");
-            result_clone.refresh_targets();
-            return None;
-        });
+        // all selections are watched, so the selection can be switched at runtime
+        for selection in Selection::ALL {
+            let result_clone = result.clone();
+            selection.clipboard().connect_local("owner-change", true, move |_value| {
+                let result_clone = result_clone.clone();
+                if result_clone.selection() != selection {
+                    return None;
+                }
+                println!("{} changed!", selection.name());
+                result_clone.refresh_targets();
+                return None;
+            });
+        }
 
         result.select_default_target();
 
         return result;
     }
 
+    pub fn selection(&self) -> Selection {
+        return *self.selection.read();
+    }
+
+    /// Shows the targets of another selection.
+    pub fn set_selection(&self, selection: Selection) {
+        *self.selection.write() = selection;
+        self.refresh_targets();
+    }
+
     pub fn refresh_targets(&self) {
-        let model = create_list_store();
+        let model = create_list_store(self.selection());
         self.treeview.clone().set_model(Some(&*model));
 
         self.select_default_target();
@@ -111,20 +126,20 @@ impl TargetsList {
     }
 }
 
-fn get_clipboard_targets() -> Vec<Atom> {
-    let clipboard = gtk::Clipboard::get(&DEFAULT_SELECTION);
+fn get_clipboard_targets(selection: Selection) -> Vec<Atom> {
+    let clipboard = selection.clipboard();
     let targets = clipboard.wait_for_targets();
 
     return targets.or(Some(Vec::new())).unwrap();
 }
 
-fn create_list_store() -> Arc<gtk::ListStore> {
+fn create_list_store(selection: Selection) -> Arc<gtk::ListStore> {
     let col_types: [glib::Type; 1] = [
         glib::Type::STRING,
     ];
 
     let store = Arc::new(gtk::ListStore::new(&col_types));
-    let mut data = get_clipboard_targets();
+    let mut data = get_clipboard_targets(selection);
     data.sort_by(|a, b| a.name().as_str().cmp(b.name().as_str()));
     for target in data {
         let name = target.name().to_string();
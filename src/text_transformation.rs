@@ -1,6 +1,8 @@
+use std::sync::Arc;
 use crate::char_filter::CharFilter;
-use crate::config::TransformationProfile;
-use crate::{Config, Transformation, TransformationAction};
+use crate::config::{CharacterTransformation, TransformationProfile};
+use crate::transformer::{Transformer, TransformerRegistry};
+use crate::{Config, TransformationAction};
 use serde::{ Serialize, Deserialize };
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -165,7 +167,7 @@ pub struct SimpleTransformation {
 }
 
 impl SimpleTransformation {
-    pub fn new(config: &Config, trafo: &Transformation) -> anyhow::Result<SimpleTransformation> {
+    pub fn new(config: &Config, trafo: &CharacterTransformation) -> anyhow::Result<SimpleTransformation> {
         let mut filters: Vec<CharFilter> = Vec::new();
         for filter_config in trafo.filters() {
             let filter_config = filter_config.character_filter(config);
@@ -182,8 +184,10 @@ impl SimpleTransformation {
             action
         });
     }
+}
 
-    pub fn execute(&self, text: &str) -> String {
+impl Transformer for SimpleTransformation {
+    fn execute(&self, text: &str) -> anyhow::Result<String> {
         let mut output = String::new();
         for ch in text.chars() {
             let mut add_ch = true;
@@ -201,32 +205,40 @@ impl SimpleTransformation {
                 output.push(ch);
             }
         }
-        return output;
+        return Ok(output);
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone)]
 pub struct TextTransformation {
-    transformations: Vec<SimpleTransformation>
+    transformations: Vec<Arc<dyn Transformer>>
 }
 
 impl TextTransformation {
     pub fn new(config: &Config, trafo_profile: &TransformationProfile) -> anyhow::Result<TextTransformation> {
-        let mut transformations: Vec<SimpleTransformation> = Vec::new();
-        for trafo_config in trafo_profile.transformations().iter() {
-            let trafo = SimpleTransformation::new(config, trafo_config)?;
+        return Self::with_registry(config, trafo_profile, &TransformerRegistry::new());
+    }
+
+    /// Creates the transformation for a profile, using the registry to create `custom` transformations.
+    pub fn with_registry(config: &Config, trafo_profile: &TransformationProfile, registry: &TransformerRegistry)
+                         -> anyhow::Result<TextTransformation> {
+        let mut transformations: Vec<Arc<dyn Transformer>> = Vec::new();
+        for trafo_config in config.resolve_transformations(trafo_profile)?.iter() {
+            let trafo = registry.create(config, trafo_config)?;
             transformations.push(trafo);
         }
         return Ok(TextTransformation {
             transformations
         });
     }
+}
 
-    pub fn execute(&self, text: &str) -> String {
+impl Transformer for TextTransformation {
+    fn execute(&self, text: &str) -> anyhow::Result<String> {
         let mut current_text = text.to_string();
         for trafo in self.transformations.iter() {
-            current_text = trafo.execute(current_text.as_str());
+            current_text = trafo.execute(current_text.as_str())?;
         }
-        return current_text;
+        return Ok(current_text);
     }
-}
\ No newline at end of file
+}
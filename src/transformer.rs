@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use crate::byte_codec::ByteCodecTransformer;
+use crate::command_transformer::CommandTransformer;
+use crate::config::{Config, Transformation};
+use crate::emoji_transformer::EmojiTransformer;
+use crate::file_list::FileListConverter;
+use crate::html_markdown::HtmlToMarkdownConverter;
+use crate::mojibake::MojibakeRepairer;
+use crate::pii_redactor::PiiRedactor;
+use crate::script_transformer::ScriptTransformer;
+use crate::secret_detector::SecretMaskTransformer;
+use crate::snippet_cleaner::SnippetCleaner;
+use crate::structured_data::StructuredDataTransformer;
+use crate::table_converter::TableConverter;
+use crate::text_transformation::SimpleTransformation;
+
+/// A single step transforming the clipboard text.
+pub trait Transformer: Debug + Send + Sync {
+    fn execute(&self, text: &str) -> anyhow::Result<String>;
+}
+
+/// Creates a transformer for a `custom` transformation from its (untyped) options.
+pub type TransformerFactory = Box<dyn Fn(&Config, &serde_yaml::Value) -> anyhow::Result<Arc<dyn Transformer>> + Send + Sync>;
+
+/// Creates transformers from their configuration. The built-in transformation types are
+/// always available, additional types can be registered for `custom` transformations.
+pub struct TransformerRegistry {
+    factories: HashMap<String, TransformerFactory>,
+}
+
+impl TransformerRegistry {
+    pub fn new() -> TransformerRegistry {
+        return TransformerRegistry {
+            factories: HashMap::new(),
+        };
+    }
+
+    pub fn register<F>(&mut self, type_name: &str, factory: F) -> &mut Self
+        where F: Fn(&Config, &serde_yaml::Value) -> anyhow::Result<Arc<dyn Transformer>> + Send + Sync + 'static {
+        self.factories.insert(type_name.to_string(), Box::new(factory));
+        return self;
+    }
+
+    pub fn is_registered(&self, type_name: &str) -> bool {
+        return self.factories.contains_key(type_name);
+    }
+
+    pub fn create(&self, config: &Config, trafo_config: &Transformation) -> anyhow::Result<Arc<dyn Transformer>> {
+        match trafo_config {
+            Transformation::Character(trafo_config) => {
+                return Ok(Arc::new(SimpleTransformation::new(config, trafo_config)?));
+            }
+            Transformation::Emoji { emoji } => {
+                return Ok(Arc::new(EmojiTransformer::from_config(emoji)?));
+            }
+            Transformation::Script { script } => {
+                return Ok(Arc::new(ScriptTransformer::from_config(config, script)?));
+            }
+            Transformation::Command { command } => {
+                return Ok(Arc::new(CommandTransformer::from_config(config, command)?));
+            }
+            Transformation::MaskSecrets { mask_secrets } => {
+                return Ok(Arc::new(SecretMaskTransformer::from_config(config, mask_secrets)?));
+            }
+            Transformation::RedactPii { redact_pii } => {
+                return Ok(Arc::new(PiiRedactor::from_config(redact_pii)?));
+            }
+            Transformation::CodeSnippet { code_snippet } => {
+                return Ok(Arc::new(SnippetCleaner::from_config(code_snippet)?));
+            }
+            Transformation::HtmlToMarkdown { html_to_markdown } => {
+                return Ok(Arc::new(HtmlToMarkdownConverter::from_config(html_to_markdown)?));
+            }
+            Transformation::Table { table } => {
+                return Ok(Arc::new(TableConverter::from_config(table)?));
+            }
+            Transformation::StructuredData { structured_data } => {
+                return Ok(Arc::new(StructuredDataTransformer::from_config(structured_data)?));
+            }
+            Transformation::Encode { encode } => {
+                return Ok(Arc::new(ByteCodecTransformer::encoder(encode)?));
+            }
+            Transformation::Decode { decode } => {
+                return Ok(Arc::new(ByteCodecTransformer::decoder(decode)?));
+            }
+            Transformation::FileList { file_list } => {
+                return Ok(Arc::new(FileListConverter::from_config(config, file_list)?));
+            }
+            Transformation::RepairMojibake { repair_mojibake } => {
+                return Ok(Arc::new(MojibakeRepairer::from_config(repair_mojibake)?));
+            }
+            Transformation::Custom { custom } => {
+                let factory = self.factories.get(custom.type_name())
+                    .ok_or_else(|| anyhow::Error::msg(format!(
+                        "Unknown custom transformation type \"{}\".", custom.type_name())))?;
+                return factory(config, custom.options());
+            }
+        }
+    }
+}
+
+impl Default for TransformerRegistry {
+    fn default() -> Self {
+        return TransformerRegistry::new();
+    }
+}
+
+impl Debug for TransformerRegistry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return f.debug_struct("TransformerRegistry")
+            .field("custom_types", &self.factories.keys().collect::<Vec<&String>>())
+            .finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text_transformation::TextTransformation;
+
+    #[derive(Debug)]
+    struct Suffix(String);
+
+    impl Transformer for Suffix {
+        fn execute(&self, text: &str) -> anyhow::Result<String> {
+            return Ok(format!("{}{}", text, self.0));
+        }
+    }
+
+    fn suffix_registry() -> TransformerRegistry {
+        let mut registry = TransformerRegistry::new();
+        registry.register("suffix", |_config, options| {
+            let suffix = options.get("text").and_then(|text| text.as_str())
+                .ok_or_else(|| anyhow::Error::msg("suffix needs a \"text\" option"))?;
+            return Ok(Arc::new(Suffix(suffix.to_string())));
+        });
+        return registry;
+    }
+
+    fn config() -> Config {
+        return serde_yaml::from_str(r#"
+filters: {}
+profiles:
+  - name: "suffixed"
+    transformations:
+      - emoji: { mode: remove }
+      - custom: { type: suffix, options: { text: "!" } }
+"#).unwrap();
+    }
+
+    #[test]
+    fn creates_registered_custom_types_from_yaml() {
+        let registry = suffix_registry();
+        assert!(registry.is_registered("suffix") && !registry.is_registered("prefix"));
+        let trafo_config: Transformation = serde_yaml::from_str("custom: { type: suffix, options: { text: \"?\" } }").unwrap();
+        let trafo = registry.create(&config(), &trafo_config).unwrap();
+        assert_eq!(trafo.execute("why").unwrap(), "why?");
+        let trafo_config: Transformation = serde_yaml::from_str("custom: { type: suffix }").unwrap();
+        let err = registry.create(&config(), &trafo_config).unwrap_err();
+        assert_eq!(err.to_string(), "suffix needs a \"text\" option");
+    }
+
+    #[test]
+    fn rejects_unregistered_custom_types() {
+        let trafo_config: Transformation = serde_yaml::from_str("custom: { type: suffix }").unwrap();
+        let err = TransformerRegistry::new().create(&config(), &trafo_config).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown custom transformation type \"suffix\".");
+    }
+
+    #[test]
+    fn profiles_create_custom_types_with_the_given_registry() {
+        let config = config();
+        let profile = config.profile("suffixed").unwrap();
+        let trafo = TextTransformation::with_registry(&config, profile, &suffix_registry()).unwrap();
+        assert_eq!(trafo.execute("done 🎉").unwrap(), "done !");
+        let err = TextTransformation::new(&config, profile).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown custom transformation type \"suffix\".");
+    }
+}